@@ -0,0 +1,261 @@
+//! Générateur de charge : `rb-bench` ouvre de nombreuses connexions simultanées vers un
+//! listener, y envoie des requêtes à un débit cible ou aussi vite que possible, et mesure le
+//! débit, les latences, les erreurs et la répartition des réponses entre serveurs cibles.
+//!
+//! Face à des serveurs `echo_server --identify`, chaque réponse est l'adresse du serveur qui
+//! l'a envoyée : le rapport montre alors comment le load balancer répartit la charge.
+
+pub mod report;
+
+use std::net::{Ipv4Addr, SocketAddr, ToSocketAddrs};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpSocket, TcpStream};
+use tokio::task::JoinSet;
+use tokio::time::{Instant, MissedTickBehavior};
+
+use rb_test_backends::echo::ERROR_REPLY;
+pub use report::Report;
+use report::Stats;
+
+// Adresse visée par défaut : celle du load balancer
+const DEFAULT_TARGET: &str = "127.0.0.1:7878";
+
+// Première adresse source utilisée avec `--sources`
+const FIRST_SOURCE: Ipv4Addr = Ipv4Addr::new(127, 0, 1, 1);
+
+/// Configuration de `rb-bench`, lue depuis la ligne de commande.
+///
+/// Options reconnues :
+///
+/// * `--target <adresse>` - adresse visée, `ip:port`, `[ipv6]:port` ou `nom:port` (par défaut `127.0.0.1:7878`).
+/// * `--connections <n>` - nombre de connexions simultanées (par défaut 10).
+/// * `--requests-per-connection <n>` - requêtes envoyées sur chaque connexion avant d'en ouvrir une
+///   nouvelle (par défaut 1).
+/// * `--payload <texte>` - contenu de chaque requête (par défaut `ping`).
+/// * `--payload-size <octets>` - requêtes de la taille donnée au lieu de `--payload`.
+/// * `--rate <par seconde>` - débit total de requêtes visé (aussi vite que possible par défaut).
+/// * `--duration <secondes>` - durée de la mesure (par défaut 10).
+/// * `--timeout <secondes>` - délai maximal de connexion et de réponse (par défaut 5).
+/// * `--sources <n>` - répartit les connexions sur `n` adresses sources `127.0.1.1`, `127.0.1.2`, …
+///   pour que l'affinité par adresse IP ne ramène pas tout vers un seul serveur (désactivé par défaut,
+///   sans effet vers une cible IPv6).
+#[derive(Clone, Debug)]
+pub struct Options {
+    pub target: SocketAddr,
+    pub connections: usize,
+    pub requests_per_connection: usize,
+    pub payload: Vec<u8>,
+    pub rate: Option<f64>,
+    pub duration: Duration,
+    pub timeout: Duration,
+    pub sources: Option<u32>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            target: DEFAULT_TARGET.parse().unwrap(),
+            connections: 10,
+            requests_per_connection: 1,
+            payload: b"ping".to_vec(),
+            rate: None,
+            duration: Duration::from_secs(10),
+            timeout: Duration::from_secs(5),
+            sources: None,
+        }
+    }
+}
+
+impl Options {
+    /// Lit la configuration depuis une liste d'arguments (sans le nom du programme).
+    ///
+    /// # Errors
+    ///
+    /// Retourne un message d'erreur si une option est inconnue, n'a pas de valeur ou a une valeur invalide.
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self, String> {
+        let mut options = Self::default();
+        let mut rate_arg = String::new();
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("missing value for {}", arg));
+            match arg.as_str() {
+                "--target" => {
+                    let target = value()?;
+                    let resolved = target.to_socket_addrs().ok().and_then(|mut addrs| addrs.next());
+                    options.target = resolved.ok_or_else(|| format!("invalid address: {}", target))?;
+                }
+                "--connections" => options.connections = parse_count(&value()?)?,
+                "--requests-per-connection" => options.requests_per_connection = parse_count(&value()?)?,
+                "--payload" => options.payload = value()?.into_bytes(),
+                "--payload-size" => options.payload = vec![b'x'; parse_count(&value()?)?],
+                "--rate" => {
+                    let rate = value()?;
+                    let parsed = rate.parse::<f64>().ok().filter(|r| r.is_finite() && *r > 0.0);
+                    options.rate = Some(parsed.ok_or_else(|| format!("invalid rate: {}", rate))?);
+                    rate_arg = rate;
+                }
+                "--duration" => options.duration = parse_secs(&value()?)?,
+                "--timeout" => options.timeout = parse_secs(&value()?)?,
+                "--sources" => {
+                    let sources = value()?;
+                    let parsed = sources.parse().ok().filter(|n| (1..=65_000).contains(n));
+                    options.sources = Some(parsed.ok_or_else(|| format!("invalid source count: {}", sources))?);
+                }
+                _ => return Err(format!("unknown option: {}", arg)),
+            }
+        }
+
+        // Le débit se vérifie une fois le nombre de connexions connu, qu'il soit donné avant ou après
+        if let Some(rate) = options.rate {
+            if rate_period(rate, options.connections).is_none() {
+                return Err(format!("invalid rate: {}", rate_arg));
+            }
+        }
+        Ok(options)
+    }
+}
+
+// Intervalle entre deux requêtes d'une même connexion pour un débit total de `rate` requêtes par
+// seconde, ou `None` s'il est nul ou trop grand pour une `Duration`
+fn rate_period(rate: f64, connections: usize) -> Option<Duration> {
+    Duration::try_from_secs_f64(connections as f64 / rate)
+        .ok()
+        .filter(|period| !period.is_zero())
+}
+
+/// Envoie des requêtes pendant `options.duration` et retourne le rapport de la mesure.
+pub async fn run(options: Options) -> Report {
+    let start = Instant::now();
+    let deadline = start + options.duration;
+
+    let mut clients = JoinSet::new();
+    for index in 0..options.connections {
+        clients.spawn(client(index, options.clone(), start, deadline));
+    }
+    let mut stats = Stats::default();
+    while let Some(result) = clients.join_next().await {
+        stats.merge(result.expect("bench client panicked"));
+    }
+    Report::new(start.elapsed(), stats)
+}
+
+// Client qui enchaîne les requêtes jusqu'à l'échéance, en rouvrant une connexion après
+// `requests_per_connection` requêtes ou après une erreur
+async fn client(index: usize, options: Options, start: Instant, deadline: Instant) -> Stats {
+    let mut stats = Stats::default();
+    let mut buf = vec![0; 64 * 1024];
+
+    // Avec un débit visé, chaque client en prend une part égale, décalée pour lisser l'envoi
+    // (un débit que `Options::parse` aurait refusé laisse le client envoyer aussi vite que possible)
+    let mut ticker = options.rate.and_then(|rate| rate_period(rate, options.connections)).map(|period| {
+        let offset = period.mul_f64(index as f64 / options.connections as f64);
+        let mut ticker = tokio::time::interval_at(start + offset, period);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        ticker
+    });
+
+    let mut connection: Option<(TcpStream, usize)> = None;
+    let mut opened = 0;
+    loop {
+        if let Some(ticker) = &mut ticker {
+            ticker.tick().await;
+        }
+        if Instant::now() >= deadline {
+            break;
+        }
+
+        let (socket, sent) = match &mut connection {
+            Some(connection) => connection,
+            None => {
+                // Chaque nouvelle connexion part de l'adresse source suivante
+                let source = options.sources.map(|n| source_addr(index + opened * options.connections, n));
+                opened += 1;
+                match connect(options.target, source, options.timeout).await {
+                    Ok(socket) => connection.insert((socket, 0)),
+                    Err(kind) => {
+                        stats.error(kind);
+                        continue;
+                    }
+                }
+            }
+        };
+
+        let sent_at = Instant::now();
+        match exchange(socket, &options.payload, &mut buf, options.timeout).await {
+            Ok(n) => {
+                stats.latencies.push(sent_at.elapsed());
+                stats.bytes_sent += options.payload.len() as u64;
+                stats.bytes_received += n as u64;
+                let reply = &buf[..n];
+                if reply == ERROR_REPLY {
+                    stats.error("error_reply");
+                } else {
+                    *stats.responses.entry(String::from_utf8_lossy(reply).into_owned()).or_insert(0) += 1;
+                }
+                *sent += 1;
+                if *sent >= options.requests_per_connection {
+                    connection = None;
+                }
+            }
+            Err(kind) => {
+                stats.error(kind);
+                connection = None;
+            }
+        }
+    }
+    stats
+}
+
+// Adresse source d'indice `index` parmi `count`
+fn source_addr(index: usize, count: u32) -> Ipv4Addr {
+    Ipv4Addr::from(u32::from(FIRST_SOURCE) + (index as u32 % count))
+}
+
+// Ouvre une connexion vers `target`, éventuellement depuis une adresse source IPv4 donnée
+async fn connect(target: SocketAddr, source: Option<Ipv4Addr>, timeout: Duration) -> Result<TcpStream, &'static str> {
+    let socket = if target.is_ipv4() { TcpSocket::new_v4() } else { TcpSocket::new_v6() }.map_err(|_| "connect")?;
+    if let Some(source) = source.filter(|_| target.is_ipv4()) {
+        socket.bind(SocketAddr::from((source, 0))).map_err(|_| "connect")?;
+    }
+    match tokio::time::timeout(timeout, socket.connect(target)).await {
+        Ok(Ok(stream)) => Ok(stream),
+        Ok(Err(_)) => Err("connect"),
+        Err(_) => Err("timeout"),
+    }
+}
+
+// Envoie une requête et attend le premier morceau de la réponse
+async fn exchange(socket: &mut TcpStream, payload: &[u8], buf: &mut [u8], timeout: Duration) -> Result<usize, &'static str> {
+    let io = async {
+        socket.write_all(payload).await?;
+        socket.read(buf).await
+    };
+    match tokio::time::timeout(timeout, io).await {
+        Ok(Ok(0)) => Err("closed"),
+        Ok(Ok(n)) => Ok(n),
+        Ok(Err(e)) if e.kind() == std::io::ErrorKind::ConnectionReset => Err("reset"),
+        Ok(Err(_)) => Err("io"),
+        Err(_) => Err("timeout"),
+    }
+}
+
+// Convertit un nombre de secondes (éventuellement décimal) en durée strictement positive
+fn parse_secs(value: &str) -> Result<Duration, String> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|secs| secs.is_finite() && *secs > 0.0)
+        .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+        .ok_or_else(|| format!("invalid duration: {}", value))
+}
+
+// Convertit un nombre strictement positif
+fn parse_count(value: &str) -> Result<usize, String> {
+    value
+        .parse::<usize>()
+        .ok()
+        .filter(|n| *n > 0)
+        .ok_or_else(|| format!("invalid count: {}", value))
+}
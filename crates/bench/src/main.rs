@@ -0,0 +1,22 @@
+use std::process::ExitCode;
+
+use rb_bench::Options;
+
+// Lance la mesure décrite par la ligne de commande et affiche son rapport
+#[tokio::main]
+async fn main() -> ExitCode {
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::from(2);
+        }
+    };
+
+    println!(
+        "Running {} connection(s) against {} for {:?}",
+        options.connections, options.target, options.duration
+    );
+    print!("{}", rb_bench::run(options).await);
+    ExitCode::SUCCESS
+}
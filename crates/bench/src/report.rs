@@ -0,0 +1,114 @@
+//! Résultats d'une mesure : débit, latences, erreurs et réponses par serveur.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::Duration;
+
+// Percentiles de latence affichés dans le rapport
+const PERCENTILES: [f64; 4] = [50.0, 90.0, 99.0, 99.9];
+
+/// Résultats d'un client, fusionnés à la fin de la mesure.
+#[derive(Default)]
+pub(crate) struct Stats {
+    pub(crate) latencies: Vec<Duration>,
+    pub(crate) errors: BTreeMap<&'static str, u64>,
+    pub(crate) responses: BTreeMap<String, u64>,
+    pub(crate) bytes_sent: u64,
+    pub(crate) bytes_received: u64,
+}
+
+impl Stats {
+    pub(crate) fn error(&mut self, kind: &'static str) {
+        *self.errors.entry(kind).or_insert(0) += 1;
+    }
+
+    pub(crate) fn merge(&mut self, other: Stats) {
+        self.latencies.extend(other.latencies);
+        for (kind, count) in other.errors {
+            *self.errors.entry(kind).or_insert(0) += count;
+        }
+        for (reply, count) in other.responses {
+            *self.responses.entry(reply).or_insert(0) += count;
+        }
+        self.bytes_sent += other.bytes_sent;
+        self.bytes_received += other.bytes_received;
+    }
+}
+
+/// Rapport d'une mesure.
+pub struct Report {
+    elapsed: Duration,
+    stats: Stats,
+}
+
+impl Report {
+    pub(crate) fn new(elapsed: Duration, mut stats: Stats) -> Self {
+        stats.latencies.sort_unstable();
+        Self { elapsed, stats }
+    }
+
+    /// Nombre de requêtes qui ont reçu une réponse, erreurs injectées comprises.
+    pub fn requests(&self) -> usize {
+        self.stats.latencies.len()
+    }
+
+    /// Requêtes servies par seconde.
+    pub fn throughput(&self) -> f64 {
+        self.requests() as f64 / self.elapsed.as_secs_f64()
+    }
+
+    /// Latence au percentile `p` (entre 0 et 100), par la méthode du rang le plus proche.
+    pub fn percentile(&self, p: f64) -> Option<Duration> {
+        let latencies = &self.stats.latencies;
+        let rank = (p / 100.0 * latencies.len() as f64).ceil() as usize;
+        latencies.get(rank.clamp(1, latencies.len().max(1)) - 1).copied()
+    }
+
+    /// Nombre d'erreurs par type : `connect`, `timeout`, `closed`, `reset`, `io` et `error_reply`.
+    pub fn errors(&self) -> &BTreeMap<&'static str, u64> {
+        &self.stats.errors
+    }
+
+    /// Nombre de réponses par contenu. Avec `echo_server --identify`, chaque clé est
+    /// l'adresse du serveur qui a répondu.
+    pub fn responses(&self) -> &BTreeMap<String, u64> {
+        &self.stats.responses
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let secs = self.elapsed.as_secs_f64();
+        writeln!(f, "Duration:   {:.2}s", secs)?;
+        writeln!(f, "Requests:   {} ({:.0} req/s)", self.requests(), self.throughput())?;
+        writeln!(
+            f,
+            "Bandwidth:  {:.0} B/s sent, {:.0} B/s received",
+            self.stats.bytes_sent as f64 / secs,
+            self.stats.bytes_received as f64 / secs
+        )?;
+
+        writeln!(f, "Latency:")?;
+        for p in PERCENTILES {
+            if let Some(latency) = self.percentile(p) {
+                writeln!(f, "  p{:<6} {:?}", p, latency)?;
+            }
+        }
+        if let Some(max) = self.stats.latencies.last() {
+            writeln!(f, "  max     {:?}", max)?;
+        }
+
+        let errors: u64 = self.stats.errors.values().sum();
+        writeln!(f, "Errors:     {}", errors)?;
+        for (kind, count) in &self.stats.errors {
+            writeln!(f, "  {:<12} {}", kind, count)?;
+        }
+
+        let total: u64 = self.stats.responses.values().sum();
+        writeln!(f, "Responses:")?;
+        for (reply, count) in &self.stats.responses {
+            writeln!(f, "  {:<24} {:>8} ({:.1}%)", reply, count, *count as f64 * 100.0 / total as f64)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,53 @@
+use std::time::Duration;
+
+use rb_bench::Options;
+use rb_test_utils::harness::Harness;
+
+#[tokio::test]
+async fn reports_distribution_across_backends() {
+    let mut harness = Harness::start(2, &[]).await;
+    let options = Options {
+        target: harness.addr,
+        connections: 4,
+        duration: Duration::from_millis(300),
+        sources: Some(50),
+        ..Options::default()
+    };
+    let report = rb_bench::run(options).await;
+
+    assert!(report.requests() > 0);
+    assert!(report.errors().is_empty(), "unexpected errors: {:?}", report.errors());
+    for index in 0..2 {
+        assert!(report.responses().contains_key(&harness.backend_addr(index).to_string()));
+    }
+    assert!(report.percentile(50.0) <= report.percentile(99.0));
+    assert!(harness.shutdown().await);
+}
+
+#[tokio::test]
+async fn paces_requests_at_the_target_rate() {
+    let mut harness = Harness::start(1, &[]).await;
+    let options = Options {
+        target: harness.addr,
+        connections: 2,
+        rate: Some(20.0),
+        duration: Duration::from_millis(500),
+        ..Options::default()
+    };
+    let report = rb_bench::run(options).await;
+
+    // 20 requêtes par seconde pendant une demi-seconde
+    assert!((8..=12).contains(&report.requests()), "{} requests", report.requests());
+    assert!(harness.shutdown().await);
+}
+
+#[test]
+fn refuses_rates_without_a_usable_period() {
+    let parse = |args: &[&str]| Options::parse(args.iter().map(|arg| arg.to_string()));
+
+    // Une requête toutes les 1e300 secondes ne tient pas dans une `Duration`
+    assert_eq!(parse(&["--rate", "1e-300"]).unwrap_err(), "invalid rate: 1e-300");
+    // Avec 10 connexions, 1e12 requêtes par seconde donnent un intervalle nul par connexion
+    assert_eq!(parse(&["--rate", "1e12", "--connections", "10"]).unwrap_err(), "invalid rate: 1e12");
+    assert_eq!(parse(&["--rate", "20", "--connections", "2"]).unwrap().rate, Some(20.0));
+}
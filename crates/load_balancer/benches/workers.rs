@@ -0,0 +1,109 @@
+//! Compare le débit en connexions par seconde d'un load balancer à un seul socket d'écoute
+//! avec celui d'un load balancer à plusieurs workers `SO_REUSEPORT`.
+//!
+//! ```sh
+//! cargo bench --bench workers
+//! ```
+//!
+//! La sortie standard du load balancer est lue jusqu'à la ligne qui donne son adresse, puis vidée
+//! en tâche de fond ; sa sortie d'erreur est ignorée. Seuls les résultats s'affichent, et les logs
+//! de chaque connexion ne ralentissent pas la mesure.
+
+use std::net::SocketAddr;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::process::Command;
+
+// Nombre de clients simultanés et durée de chaque mesure
+const CLIENTS: usize = 64;
+const DURATION: Duration = Duration::from_secs(3);
+
+#[tokio::main]
+async fn main() {
+    let backend = spawn_backend().await;
+    let cpus = std::thread::available_parallelism().map_or(1, |n| n.get()).to_string();
+
+    let scenarios: [(&str, Vec<&str>); 3] = [
+        ("single listener", vec![]),
+        ("reuseport workers, shared runtime", vec!["--workers", &cpus]),
+        ("reuseport workers, pinned runtimes", vec!["--workers", &cpus, "--pin-cpus"]),
+    ];
+
+    for (name, args) in scenarios {
+        let rate = measure(backend, &args).await;
+        println!("{:<36} {:>10.0} connections/s", name, rate);
+    }
+}
+
+// Démarre un load balancer avec les options données et mesure son débit
+async fn measure(backend: SocketAddr, args: &[&str]) -> f64 {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_load_balancer"))
+        .args(["--listen", "127.0.0.1:0", "--server", &backend.to_string()])
+        .args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .expect("failed to start load_balancer");
+
+    let mut lines = BufReader::new(child.stdout.take().unwrap()).lines();
+    let addr: SocketAddr = loop {
+        let line = lines.next_line().await.unwrap().expect("load_balancer exited before listening");
+        if let Some(addr) = line.strip_prefix("Load balancer running on ") {
+            break addr.parse().unwrap();
+        }
+    };
+    // Vide la sortie en continu, sans l'afficher, pour que le load balancer ne bloque pas sur un tube plein
+    tokio::spawn(async move { while let Ok(Some(_)) = lines.next_line().await {} });
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let done = Arc::new(AtomicU64::new(0));
+    let mut clients = Vec::new();
+    for _ in 0..CLIENTS {
+        let stop = stop.clone();
+        let done = done.clone();
+        clients.push(tokio::spawn(async move {
+            let mut buf = [0; 64];
+            while !stop.load(Ordering::Relaxed) {
+                let Ok(mut socket) = TcpStream::connect(addr).await else { continue };
+                if socket.write_all(b"ping").await.is_ok() && matches!(socket.read(&mut buf).await, Ok(n) if n > 0) {
+                    done.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }));
+    }
+
+    let start = Instant::now();
+    tokio::time::sleep(DURATION).await;
+    stop.store(true, Ordering::Relaxed);
+    let elapsed = start.elapsed();
+    for client in clients {
+        let _ = client.await;
+    }
+    let _ = child.kill().await;
+
+    done.load(Ordering::Relaxed) as f64 / elapsed.as_secs_f64()
+}
+
+// Serveur cible qui répond immédiatement à chaque lecture
+async fn spawn_backend() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut buf = [0; 64];
+                while let Ok(n) = socket.read(&mut buf).await {
+                    if n == 0 || socket.write_all(b"pong").await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    });
+    addr
+}
@@ -0,0 +1,137 @@
+use std::process::ExitCode;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::watch;
+
+use rustic_balancer::upgrade::{self, Handoff, HandoffEnv, UpgradeSignal};
+use rustic_balancer::{shutdown, Balancer, Config, Listener};
+
+fn main() -> tokio::io::Result<ExitCode> {
+    // Retire les variables de mise à jour à chaud tant que le processus n'a qu'un thread
+    let handoff_env = HandoffEnv::take();
+    tokio::runtime::Builder::new_multi_thread().enable_all().build()?.block_on(run(handoff_env))
+}
+
+// Fonction principale exécutée de manière asynchrone
+async fn run(handoff_env: HandoffEnv) -> tokio::io::Result<ExitCode> {
+    // Lit la configuration depuis la ligne de commande
+    let mut config = match Config::from_args() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            return Ok(ExitCode::from(2));
+        }
+    };
+
+    // Installe les gestionnaires de signaux avant d'accepter la moindre connexion
+    let shutdown = shutdown::signal()?;
+    tokio::pin!(shutdown);
+    let mut upgrade = UpgradeSignal::new()?;
+
+    // Reprend les sockets d'écoute du processus précédent lors d'une mise à jour à chaud,
+    // sinon prépare le load balancer sur l'adresse configurée
+    let mut handoff = Handoff::inherited(handoff_env)?;
+    let listener = match handoff.as_mut() {
+        Some(handoff) => {
+            let listener = Listener::from_std(std::mem::take(&mut handoff.listeners));
+            if let Some(mode) = config.unix_mode {
+                listener.set_permissions(mode)?;
+            }
+            listener
+        }
+        None => bind_listener(&config).await?,
+    };
+    println!("Load balancer running on {}", listener.local_addr()?);
+
+    // Garde une copie des sockets pour pouvoir les transmettre à un successeur
+    let mut upgrade_listeners = listener.try_clone()?;
+
+    // Crée le pool et les compteurs partagés entre les tâches et les workers
+    let admin = config.admin.take();
+    let balancer = match Balancer::new(config) {
+        Ok(balancer) => balancer,
+        Err(e) => {
+            eprintln!("{}", e);
+            return Ok(ExitCode::from(2));
+        }
+    };
+
+    // Démarre l'interface d'administration si elle est demandée
+    if let Some(admin) = &admin {
+        let listener = TcpListener::bind(admin).await?;
+        println!("Admin interface running on {}", listener.local_addr()?);
+        tokio::spawn(Arc::clone(&balancer).serve_admin(listener));
+    }
+
+    // Démarre les workers, qui s'arrêtent quand `stop_tx` passe à `true`
+    let (stop_tx, mut stop_rx) = watch::channel(false);
+    let stop = async move {
+        let _ = stop_rx.wait_for(|stop| *stop).await;
+    };
+    let mut running = tokio::spawn(balancer.run(listener, stop));
+
+    // Les workers acceptent : le processus précédent peut s'arrêter
+    if let Some(handoff) = handoff {
+        handoff.notify_parent()?;
+    }
+
+    // Attend un signal d'arrêt, en lançant un successeur à chaque demande de mise à jour
+    let mut successor: Option<std::process::Child> = None;
+    let drained = loop {
+        tokio::select! {
+            // Lance le processus successeur, qui nous enverra SIGTERM une fois prêt. Un seul
+            // successeur à la fois : deux reprendraient les mêmes sockets et serviraient chacun
+            // de leur côté
+            _ = upgrade.recv() => {
+                if let Some(child) = successor.as_mut() {
+                    match child.try_wait() {
+                        Ok(None) => {
+                            eprintln!("Upgrade already in progress with successor pid {}, ignoring", child.id());
+                            continue;
+                        }
+                        _ => eprintln!("Successor pid {} exited before taking over", child.id()),
+                    }
+                }
+                match upgrade::spawn_successor(&upgrade_listeners) {
+                    Ok(child) => {
+                        println!("Upgrade requested, started successor with pid {}", child.id());
+                        successor = Some(child);
+                    }
+                    Err(e) => {
+                        eprintln!("Failed to start successor: {}", e);
+                        successor = None;
+                    }
+                }
+            }
+            // Les workers ne s'arrêtent d'eux-mêmes que sur une erreur d'acceptation
+            result = &mut running => break result.map_err(tokio::io::Error::other)??,
+            _ = &mut shutdown => {
+                // Arrête d'accepter immédiatement, puis laisse les connexions en cours se terminer
+                upgrade_listeners.clear();
+                let _ = stop_tx.send(true);
+                break running.await.map_err(tokio::io::Error::other)??;
+            }
+        }
+    };
+
+    if drained {
+        println!("All connections drained, exiting");
+        Ok(ExitCode::SUCCESS)
+    } else {
+        Ok(ExitCode::from(shutdown::EXIT_DRAIN_TIMEOUT))
+    }
+}
+
+// Lie le listener sur l'adresse configurée. Avec `--unix-mode`, le fichier du socket Unix est
+// créé directement avec ces droits : l'umask, propre au processus, n'est changé que le temps de
+// le lier, à un moment du démarrage où aucune autre tâche ne crée de fichier.
+async fn bind_listener(config: &Config) -> tokio::io::Result<Listener> {
+    #[cfg(unix)]
+    if let Some(mode) = config.unix_mode {
+        let previous = unsafe { libc::umask(!mode as libc::mode_t & 0o777) };
+        let listener = Listener::bind(&config.listen, config.workers).await;
+        unsafe { libc::umask(previous) };
+        return listener;
+    }
+    Listener::bind(&config.listen, config.workers).await
+}
@@ -0,0 +1,56 @@
+use std::time::Duration;
+
+use rb_test_utils as common;
+use rustic_balancer::Config;
+
+#[tokio::test]
+async fn deny_rule_closes_matching_clients() {
+    let backend = common::spawn_backend(b"pong", Duration::ZERO).await;
+    let mut balancer = common::spawn_balancer(&["--server", &backend.to_string(), "--deny", "127.0.0.2/32", "--admin", "127.0.0.1:0"]).await;
+    let admin = balancer.wait_for_line("Admin interface running on ").await;
+
+    assert!(common::served_from("127.0.0.1", balancer.addr).await.0);
+    assert!(!common::served_from("127.0.0.2", balancer.addr).await.0);
+    assert!(common::admin_get(&admin, "/metrics").await.contains("rb_connections_closed_total{reason=\"denied\"} 1"));
+}
+
+#[tokio::test]
+async fn allow_rule_only_admits_matching_clients() {
+    let backend = common::spawn_backend(b"pong", Duration::ZERO).await;
+    let balancer = common::spawn_balancer(&["--server", &backend.to_string(), "--allow", "127.0.0.0/31", "--deny", "127.0.0.0"]).await;
+
+    // 127.0.0.0/31 couvre .0 et .1, mais .0 est refusé explicitement
+    assert!(common::served_from("127.0.0.1", balancer.addr).await.0);
+    assert!(!common::served_from("127.0.0.2", balancer.addr).await.0);
+}
+
+#[tokio::test]
+async fn acl_file_is_reloaded_when_modified() {
+    let dir = std::env::temp_dir().join(format!("rb-acl-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("acl.txt");
+    std::fs::write(&file, "# clients bloqués\ndeny 127.0.0.2\n").unwrap();
+
+    let backend = common::spawn_backend(b"pong", Duration::ZERO).await;
+    let mut balancer = common::spawn_balancer(&["--server", &backend.to_string(), "--acl-file", file.to_str().unwrap(), "--acl-reload", "0.05"]).await;
+    assert!(common::served_from("127.0.0.1", balancer.addr).await.0);
+    assert!(!common::served_from("127.0.0.2", balancer.addr).await.0);
+
+    std::fs::write(&file, "deny 127.0.0.1\n").unwrap();
+    balancer.wait_for_line("Reloaded access list").await;
+    assert!(!common::served_from("127.0.0.1", balancer.addr).await.0);
+    assert!(common::served_from("127.0.0.2", balancer.addr).await.0);
+
+    // Une version invalide garde les règles précédentes
+    std::fs::write(&file, "deny not-an-address\n").unwrap();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(!common::served_from("127.0.0.1", balancer.addr).await.0);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn acl_reload_interval_must_be_positive() {
+    assert!(Config::parse(["--acl-reload".to_string(), "0".to_string()]).is_err());
+    assert!(Config::parse(["--acl-reload".to_string(), "0.5".to_string()]).is_ok());
+}
@@ -0,0 +1,50 @@
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use rb_test_utils as common;
+
+// Taille de la réponse du serveur cible
+const REPLY_SIZE: usize = 64 * 1024;
+
+// Démarre un serveur cible qui répond `REPLY_SIZE` octets à chaque message
+async fn spawn_bulk_backend() -> SocketAddr {
+    common::spawn_backend(Box::leak(vec![b'x'; REPLY_SIZE].into_boxed_slice()), Duration::ZERO).await
+}
+
+// Envoie `ping` et attend d'avoir reçu toute la réponse
+async fn download(addr: SocketAddr) {
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    client.write_all(b"ping").await.unwrap();
+    let mut buf = vec![0; REPLY_SIZE];
+    client.read_exact(&mut buf).await.unwrap();
+}
+
+#[tokio::test]
+async fn connection_download_is_throttled() {
+    let backend = spawn_bulk_backend().await;
+    let unlimited = common::spawn_balancer(&["--server", &backend.to_string()]).await;
+    let start = Instant::now();
+    download(unlimited.addr).await;
+    assert!(start.elapsed() < Duration::from_millis(500));
+
+    // Une seconde de débit passe d'un coup, le reste au débit demandé
+    let throttled = common::spawn_balancer(&["--server", &backend.to_string(), "--connection-bandwidth", "download=32k"]).await;
+    let start = Instant::now();
+    download(throttled.addr).await;
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_millis(900) && elapsed < Duration::from_secs(3), "{:?}", elapsed);
+}
+
+#[tokio::test]
+async fn backend_limit_is_shared_across_connections() {
+    let backend = spawn_bulk_backend().await;
+    let balancer = common::spawn_balancer(&["--server", &format!("{},download=64k", backend)]).await;
+
+    // Chaque connexion tiendrait dans la rafale, mais pas les deux ensemble
+    let start = Instant::now();
+    tokio::join!(download(balancer.addr), download(balancer.addr));
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_millis(900) && elapsed < Duration::from_secs(3), "{:?}", elapsed);
+}
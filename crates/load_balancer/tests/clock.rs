@@ -0,0 +1,56 @@
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use rb_test_utils::harness::Harness;
+use rustic_balancer::ratelimit::{ClientLimiter, ClientLimits, Rate};
+use rustic_balancer::{ManualClock, Termination};
+
+#[test]
+fn rate_limit_refills_with_the_clock() {
+    let clock = Arc::new(ManualClock::new());
+    let limits = ClientLimits { ip_rate: Some(Rate { per_second: 1.0, burst: 2.0 }), ..ClientLimits::default() };
+    let limiter = Arc::new(ClientLimiter::new(limits).with_clock(clock.clone()));
+    let ip: IpAddr = "10.0.0.1".parse().unwrap();
+
+    // La rafale est consommée, puis un jeton revient chaque seconde
+    assert!(limiter.admit(ip).is_ok());
+    assert!(limiter.admit(ip).is_ok());
+    assert!(matches!(limiter.admit(ip), Err(Termination::RateLimited)));
+    clock.advance(Duration::from_millis(900));
+    assert!(matches!(limiter.admit(ip), Err(Termination::RateLimited)));
+    clock.advance(Duration::from_millis(100));
+    assert!(limiter.admit(ip).is_ok());
+    assert!(matches!(limiter.admit(ip), Err(Termination::RateLimited)));
+}
+
+#[tokio::test]
+async fn ejection_backoff_follows_the_clock() {
+    let mut harness = Harness::start(2, &["--outlier-detection", "consecutive=1,base_ejection=10,max_ejected=50"]).await;
+
+    // Un échec suffit à éjecter le serveur arrêté, qui ne reçoit plus rien une fois relancé
+    harness.kill(0).await;
+    let mut ip = 0;
+    let mut next_ip = || {
+        ip += 1;
+        format!("127.0.5.{}", ip)
+    };
+    while harness.request(&next_ip()).await.is_some() {}
+    harness.revive(0).await;
+    for _ in 0..20 {
+        assert_eq!(harness.request(&next_ip()).await, Some(1));
+    }
+
+    // L'éjection dure encore juste avant 10 secondes, puis le serveur est réintégré après un essai
+    harness.clock.advance(Duration::from_millis(9900));
+    for _ in 0..20 {
+        assert_eq!(harness.request(&next_ip()).await, Some(1));
+    }
+    harness.clock.advance(Duration::from_millis(100));
+    let mut served = [0; 2];
+    for _ in 0..40 {
+        served[harness.request(&next_ip()).await.unwrap()] += 1;
+    }
+    assert!(served[0] > 0);
+    assert!(harness.shutdown().await);
+}
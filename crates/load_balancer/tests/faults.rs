@@ -0,0 +1,102 @@
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use rb_test_utils as common;
+use rb_test_utils::harness::Harness;
+use rustic_balancer::Fault;
+
+// Envoie `ping` et lit tout ce que le load balancer renvoie jusqu'à la fermeture
+async fn exchange(addr: std::net::SocketAddr) -> std::io::Result<Vec<u8>> {
+    let mut client = TcpStream::connect(addr).await?;
+    client.write_all(b"ping").await?;
+    let mut reply = Vec::new();
+    let mut buf = [0; 64];
+    loop {
+        match tokio::time::timeout(Duration::from_secs(5), client.read(&mut buf)).await.expect("connection stayed open")? {
+            0 => return Ok(reply),
+            n => reply.extend_from_slice(&buf[..n]),
+        }
+    }
+}
+
+#[tokio::test]
+async fn faults_are_injected_and_toggled_at_runtime() {
+    let backend = common::spawn_closing_backend(b"pong").await;
+    let mut balancer = common::spawn_balancer(&["--server", &backend.to_string(), "--fault", "default:abort=503", "--admin", "127.0.0.1:0"]).await;
+    let admin = balancer.wait_for_line("Admin interface running on ").await;
+
+    // Réponse d'erreur HTTP sans contacter le serveur cible
+    let reply = exchange(balancer.addr).await.unwrap();
+    assert!(reply.starts_with(b"HTTP/1.1 503 "), "{:?}", String::from_utf8_lossy(&reply));
+    assert_eq!(common::admin_get(&admin, "/faults").await, "default abort=503,percent=100\n");
+
+    // Réinitialisation de la connexion
+    let (status, _) = common::admin_request(&admin, "PUT", "/faults/default?reset").await;
+    assert!(status.contains("200"), "{}", status);
+    assert_eq!(exchange(balancer.addr).await.unwrap_err().kind(), std::io::ErrorKind::ConnectionReset);
+
+    // Retard puis coupure après 6 octets : `ping` passe, puis seulement `po`
+    common::admin_request(&admin, "PUT", "/faults/default?delay=0.3&drop_after=6").await;
+    let start = Instant::now();
+    assert_eq!(exchange(balancer.addr).await.unwrap(), b"po");
+    assert!(start.elapsed() >= Duration::from_millis(300));
+
+    // Sans faute, le serveur cible répond normalement
+    let (status, _) = common::admin_request(&admin, "DELETE", "/faults/default").await;
+    assert!(status.contains("200"), "{}", status);
+    assert_eq!(exchange(balancer.addr).await.unwrap(), b"pong");
+
+    let metrics = common::admin_get(&admin, "/metrics").await;
+    for reason in ["fault_abort", "fault_reset", "fault_drop"] {
+        assert!(metrics.contains(&format!("rb_connections_closed_total{{reason=\"{}\"}} 1", reason)), "{}", metrics);
+    }
+}
+
+#[tokio::test]
+async fn invalid_faults_are_refused() {
+    let backend = common::spawn_backend(b"pong", Duration::ZERO).await;
+    let mut balancer = common::spawn_balancer(&["--server", &backend.to_string(), "--admin", "127.0.0.1:0"]).await;
+    let admin = balancer.wait_for_line("Admin interface running on ").await;
+
+    let (status, body) = common::admin_request(&admin, "PUT", "/faults/blue?reset").await;
+    assert!(status.contains("400"), "{}", status);
+    assert_eq!(body, "no server in pool: blue\n");
+    let (status, _) = common::admin_request(&admin, "PUT", "/faults/default?abort=503&reset").await;
+    assert!(status.contains("400"), "{}", status);
+    let (status, _) = common::admin_request(&admin, "PUT", "/faults/default?delay=1e30").await;
+    assert!(status.contains("400"), "{}", status);
+    let (status, _) = common::admin_request(&admin, "DELETE", "/faults/default").await;
+    assert!(status.contains("404"), "{}", status);
+}
+
+#[tokio::test]
+async fn faults_spare_the_trial_of_an_ejected_backend() {
+    let mut harness = Harness::start(2, &["--outlier-detection", "consecutive=1,base_ejection=10,max_ejected=50"]).await;
+    let mut ip = 0;
+    let mut next_ip = || {
+        ip += 1;
+        format!("127.0.6.{}", ip)
+    };
+
+    // Éjecte le serveur 0, puis relance-le
+    harness.kill(0).await;
+    while harness.request(&next_ip()).await.is_some() {}
+    harness.revive(0).await;
+
+    // À la fin de l'éjection, toutes les connexions reçoivent une faute, sauf celle d'essai
+    harness.balancer.faults().set("default", Fault::parse("abort=503").unwrap());
+    harness.clock.advance(Duration::from_secs(10));
+    for _ in 0..20 {
+        harness.request(&next_ip()).await;
+    }
+
+    // L'essai a réussi : le serveur 0 reçoit de nouveau du trafic une fois les fautes retirées
+    assert!(harness.balancer.faults().remove("default"));
+    let mut served = [0; 2];
+    for _ in 0..40 {
+        served[harness.request(&next_ip()).await.unwrap()] += 1;
+    }
+    assert!(served[0] > 0, "{:?}", served);
+    assert!(harness.shutdown().await);
+}
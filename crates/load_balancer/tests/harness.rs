@@ -0,0 +1,40 @@
+use rb_test_utils::harness::Harness;
+
+// Compte les connexions servies par chaque serveur, une par adresse source `<prefix>.<i>`
+async fn served_counts(harness: &Harness, prefix: &str, backends: usize) -> Vec<usize> {
+    let mut counts = vec![0; backends];
+    for i in 1..=60 {
+        if let Some(index) = harness.request(&format!("{}.{}", prefix, i)).await {
+            counts[index] += 1;
+        }
+    }
+    counts
+}
+
+#[tokio::test]
+async fn follows_backend_failures_and_recovery() {
+    let mut harness = Harness::start(3, &[]).await;
+    assert!(served_counts(&harness, "127.0.1", 3).await.iter().all(|&n| n > 0));
+
+    // Un serveur arrêté ne sert plus aucune connexion
+    harness.kill(1).await;
+    assert_eq!(served_counts(&harness, "127.0.2", 3).await[1], 0);
+
+    // Relancé sur la même adresse, il en reçoit de nouveau
+    harness.revive(1).await;
+    assert!(served_counts(&harness, "127.0.3", 3).await[1] > 0);
+
+    assert!(harness.shutdown().await);
+}
+
+#[tokio::test]
+async fn killing_a_backend_closes_its_connections() {
+    let mut harness = Harness::start(2, &[]).await;
+    let mut client = harness.connect_from("127.0.4.1").await;
+    let index = harness.served_by(&mut client).await.expect("connection was not served");
+
+    harness.kill(index).await;
+    assert_eq!(harness.served_by(&mut client).await, None);
+    drop(client);
+    assert!(harness.shutdown().await);
+}
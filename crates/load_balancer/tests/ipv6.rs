@@ -0,0 +1,67 @@
+use std::net::IpAddr;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+use rb_test_utils::harness::Harness;
+use rustic_balancer::pool::affinity_key;
+use rustic_balancer::Config;
+
+#[tokio::test]
+async fn dual_stack_listener_serves_ipv4_and_ipv6_clients() {
+    let harness = Harness::start(2, &["--listen", "[::]:0", "--cidr-rate", "1,burst=1,v4=32,v6=64"]).await;
+    assert!(harness.addr.is_ipv6());
+    assert!(harness.request("::1").await.is_some());
+
+    // Les clients IPv4 sont vus avec leur adresse IPv4 et non `::ffff:127.0.0.x` : ils ne
+    // partagent donc pas la limite du réseau `::/64`
+    assert!(harness.request("127.0.0.1").await.is_some());
+    assert!(harness.request("127.0.0.2").await.is_some());
+    assert_eq!(harness.request("127.0.0.2").await, None);
+}
+
+#[tokio::test]
+async fn relays_to_ipv6_backend() {
+    let backend = TcpListener::bind("[::1]:0").await.unwrap();
+    let backend_addr = backend.local_addr().unwrap().to_string();
+    tokio::spawn(async move {
+        let (mut socket, _) = backend.accept().await.unwrap();
+        let mut buf = [0; 64];
+        let n = socket.read(&mut buf).await.unwrap();
+        socket.write_all(&buf[..n]).await.unwrap();
+    });
+
+    let harness = Harness::start_with(Vec::new(), &["--listen", "[::1]:0", "--server", &backend_addr]).await;
+    let mut client = harness.connect_from("::1").await;
+    client.write_all(b"ping").await.unwrap();
+    let mut buf = [0; 4];
+    client.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"ping");
+}
+
+#[test]
+fn addresses_are_parsed_with_bracketed_ipv6() {
+    for server in ["[::1]:8080", "localhost:8080", "127.0.0.1:8080", "unix:/run/app.sock"] {
+        assert!(Config::parse(["--server".to_string(), server.to_string()]).is_ok(), "{}", server);
+    }
+    for server in ["::1:8080", "[::1]", "127.0.0.1", ":8080", "unix:"] {
+        assert!(Config::parse(["--server".to_string(), server.to_string()]).is_err(), "{}", server);
+    }
+    let err = Config::parse(["--listen".to_string(), "::1:7878".to_string()]).err().unwrap();
+    assert!(err.contains("[addr]:port"), "{}", err);
+}
+
+#[test]
+fn ipv6_clients_are_grouped_by_prefix() {
+    let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+
+    // Sans regroupement, chaque adresse a sa propre affinité
+    assert_ne!(affinity_key(ip("2001:db8:1:2::5"), 128), affinity_key(ip("2001:db8:1:2::6"), 128));
+
+    // En /64, les adresses temporaires d'un même réseau partagent leur affinité
+    assert_eq!(affinity_key(ip("2001:db8:1:2::5"), 64), affinity_key(ip("2001:db8:1:2:abcd::1"), 64));
+    assert_ne!(affinity_key(ip("2001:db8:1:2::5"), 64), affinity_key(ip("2001:db8:1:3::5"), 64));
+
+    // Les adresses IPv4 ne sont pas regroupées, même écrites en IPv6
+    assert_eq!(affinity_key(ip("::ffff:10.0.0.1"), 64), "10.0.0.1");
+    assert_ne!(affinity_key(ip("10.0.0.1"), 64), affinity_key(ip("10.0.0.2"), 64));
+}
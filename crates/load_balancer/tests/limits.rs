@@ -0,0 +1,100 @@
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, ToSocketAddrs};
+
+use rb_test_utils as common;
+
+// Ouvre une connexion et vérifie qu'un échange complet passe par le load balancer
+async fn ping(addr: impl ToSocketAddrs) -> TcpStream {
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    client.write_all(b"ping").await.unwrap();
+    let mut buf = [0; 16];
+    let n = client.read(&mut buf).await.unwrap();
+    assert_eq!(&buf[..n], b"pong");
+    client
+}
+
+// Vérifie que la connexion est fermée sans réponse
+async fn assert_rejected(client: &mut TcpStream) {
+    let mut buf = [0; 16];
+    let read = tokio::time::timeout(Duration::from_secs(5), client.read(&mut buf)).await.expect("connection was not closed");
+    assert!(matches!(read, Ok(0) | Err(_)), "connection should have been rejected");
+}
+
+#[tokio::test]
+async fn listener_rejects_connections_beyond_max_connections() {
+    let backend = common::spawn_backend(b"pong", Duration::ZERO).await;
+    let mut balancer = common::spawn_balancer(&["--server", &backend.to_string(), "--max-connections", "1", "--admin", "127.0.0.1:0"]).await;
+    let admin = balancer.wait_for_line("Admin interface running on ").await;
+
+    let first = ping(balancer.addr).await;
+    let mut second = TcpStream::connect(balancer.addr).await.unwrap();
+    assert_rejected(&mut second).await;
+    assert!(common::admin_get(&admin, "/metrics").await.contains("rb_connections_closed_total{reason=\"max_connections\"} 1"));
+
+    // La place se libère à la fin de la première connexion
+    drop(first);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    ping(balancer.addr).await;
+}
+
+#[tokio::test]
+async fn full_backend_queues_then_rejects_when_queue_is_full() {
+    let backend = common::spawn_backend(b"pong", Duration::ZERO).await;
+    let server = format!("{},max_conns=1", backend);
+    let mut balancer = common::spawn_balancer(&["--server", &server, "--queue-size", "1", "--queue-timeout", "5", "--admin", "127.0.0.1:0"]).await;
+    let admin = balancer.wait_for_line("Admin interface running on ").await;
+
+    let first = ping(balancer.addr).await;
+    assert!(common::admin_get(&admin, "/metrics").await.contains(&format!("rb_backend_active_connections{{backend=\"{}\"}} 1", backend)));
+
+    // La deuxième connexion attend dans la file, la troisième ne trouve plus de place
+    let mut queued = TcpStream::connect(balancer.addr).await.unwrap();
+    queued.write_all(b"ping").await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(common::admin_get(&admin, "/metrics").await.contains("rb_connections_queued 1"));
+    let mut third = TcpStream::connect(balancer.addr).await.unwrap();
+    assert_rejected(&mut third).await;
+    assert!(common::admin_get(&admin, "/metrics").await.contains("rb_connections_closed_total{reason=\"queue_full\"} 1"));
+
+    // La connexion en file est servie dès que la première se termine
+    drop(first);
+    let mut buf = [0; 16];
+    let n = tokio::time::timeout(Duration::from_secs(5), queued.read(&mut buf)).await.unwrap().unwrap();
+    assert_eq!(&buf[..n], b"pong");
+}
+
+#[tokio::test]
+async fn queued_connection_gives_up_after_queue_timeout() {
+    let backend = common::spawn_backend(b"pong", Duration::ZERO).await;
+    let server = format!("{},max_conns=1", backend);
+    let mut balancer = common::spawn_balancer(&["--server", &server, "--queue-size", "1", "--queue-timeout", "0.2", "--admin", "127.0.0.1:0"]).await;
+    let admin = balancer.wait_for_line("Admin interface running on ").await;
+
+    let _first = ping(balancer.addr).await;
+    let mut queued = TcpStream::connect(balancer.addr).await.unwrap();
+    assert_rejected(&mut queued).await;
+    assert!(common::admin_get(&admin, "/metrics").await.contains("rb_connections_closed_total{reason=\"queue_timeout\"} 1"));
+}
+
+#[tokio::test]
+async fn running_out_of_descriptors_does_not_stop_the_listener() {
+    let backend = common::spawn_backend(b"pong", Duration::ZERO).await;
+    let mut command = tokio::process::Command::new("sh");
+    let bin = common::balancer_bin();
+    command.args(["-c", "ulimit -n 32 && exec \"$0\" \"$@\"", bin.to_str().unwrap(), "--listen", "127.0.0.1:0", "--server", &backend.to_string()]);
+    let mut balancer = common::spawn_balancer_command(command).await;
+
+    // Les connexions au-delà des descripteurs disponibles attendent dans la file du listener
+    let mut clients = Vec::new();
+    for _ in 0..32 {
+        clients.push(TcpStream::connect(balancer.addr).await.unwrap());
+    }
+    balancer.wait_for_line("Failed to accept connection: ").await;
+
+    // Une fois des descripteurs libérés, le load balancer sert à nouveau
+    drop(clients);
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert!(balancer.child.try_wait().unwrap().is_none(), "load_balancer exited");
+    ping(balancer.addr).await;
+}
@@ -0,0 +1,78 @@
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+
+use rb_test_utils as common;
+
+// Serveur fantôme qui répond n'importe quoi et transmet tout ce qu'il a reçu sur chaque connexion
+async fn spawn_shadow() -> (std::net::SocketAddr, mpsc::UnboundedReceiver<Vec<u8>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                let _ = socket.write_all(b"shadow").await;
+                let mut received = Vec::new();
+                let _ = socket.read_to_end(&mut received).await;
+                let _ = tx.send(received);
+            });
+        }
+    });
+    (addr, rx)
+}
+
+#[tokio::test]
+async fn client_bytes_are_copied_to_shadow_and_its_replies_discarded() {
+    let backend = common::spawn_backend(b"pong", Duration::ZERO).await;
+    let (shadow, mut received) = spawn_shadow().await;
+    let mut balancer = common::spawn_balancer(&[
+        "--server", &backend.to_string(),
+        "--mirror", &shadow.to_string(),
+        "--admin", "127.0.0.1:0",
+    ])
+    .await;
+    let admin = balancer.wait_for_line("Admin interface running on ").await;
+
+    let mut client = TcpStream::connect(balancer.addr).await.unwrap();
+    client.write_all(b"ping").await.unwrap();
+    let mut buf = [0; 16];
+    let n = client.read(&mut buf).await.unwrap();
+    assert_eq!(&buf[..n], b"pong");
+    client.write_all(b"again").await.unwrap();
+    client.shutdown().await.unwrap();
+    let mut rest = Vec::new();
+    client.read_to_end(&mut rest).await.unwrap();
+    assert!(rest.iter().all(|b| b"pong".contains(b)), "shadow reply leaked to the client");
+
+    let copied = tokio::time::timeout(Duration::from_secs(5), received.recv()).await.unwrap().unwrap();
+    assert_eq!(copied, b"pingagain");
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let metrics = common::admin_get(&admin, "/metrics").await;
+    assert!(metrics.contains("rb_mirror_connections_total 1"), "{}", metrics);
+    assert!(metrics.contains("rb_mirror_closed_total{reason=\"completed\"} 1"), "{}", metrics);
+}
+
+#[tokio::test]
+async fn unreachable_shadow_does_not_affect_clients() {
+    let backend = common::spawn_backend(b"pong", Duration::ZERO).await;
+    // Adresse dédiée sur laquelle personne n'écoute
+    let shadow = std::net::TcpListener::bind("127.0.0.202:0").unwrap().local_addr().unwrap();
+    let mut balancer = common::spawn_balancer(&[
+        "--server", &backend.to_string(),
+        "--mirror", &shadow.to_string(),
+        "--admin", "127.0.0.1:0",
+    ])
+    .await;
+    let admin = balancer.wait_for_line("Admin interface running on ").await;
+
+    for i in 1..=3 {
+        assert!(common::served_from(&format!("127.0.7.{}", i), balancer.addr).await.0);
+    }
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let metrics = common::admin_get(&admin, "/metrics").await;
+    assert!(metrics.contains("rb_mirror_closed_total{reason=\"connect_error\"} 3"), "{}", metrics);
+    assert!(metrics.contains("rb_connections_closed_total{reason=\"connect_error\"} 0"), "{}", metrics);
+}
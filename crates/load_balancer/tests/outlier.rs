@@ -0,0 +1,121 @@
+use std::time::Duration;
+use tokio::net::TcpListener;
+
+use rb_test_utils as common;
+use rb_test_utils::harness::Harness;
+use rustic_balancer::relay::Termination;
+
+// Réserve un port local sur lequel aucun serveur n'écoute
+fn unused_addr() -> std::net::SocketAddr {
+    std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap()
+}
+
+#[tokio::test]
+async fn failing_backend_is_ejected_then_retried_with_backoff() {
+    let live = common::spawn_backend(b"pong", Duration::ZERO).await;
+    let dead = unused_addr();
+    let mut balancer = common::spawn_balancer(&[
+        "--server", &live.to_string(),
+        "--server", &dead.to_string(),
+        "--outlier-detection", "consecutive=1,base_ejection=0.3,max_ejection=10",
+        "--admin", "127.0.0.1:0",
+    ])
+    .await;
+    let admin = balancer.wait_for_line("Admin interface running on ").await;
+
+    // Des clients différents échappent à l'affinité : une seule connexion échoue avant
+    // l'éjection, ensuite tout passe par le serveur vivant
+    let mut failures = 0;
+    for i in 1..=20 {
+        if !common::served_from(&format!("127.0.0.{}", i), balancer.addr).await.0 {
+            failures += 1;
+        }
+    }
+    assert_eq!(failures, 1);
+
+    let metrics = common::admin_get(&admin, "/metrics").await;
+    assert!(metrics.contains(&format!("rb_backend_ejected{{backend=\"{}\"}} 1", dead)));
+    assert!(metrics.contains(&format!("rb_backend_ejected{{backend=\"{}\"}} 0", live)));
+
+    // L'essai en semi-ouvert échoue : le serveur est éjecté de nouveau, deux fois plus longtemps
+    tokio::time::sleep(Duration::from_millis(400)).await;
+    let mut i = 0;
+    while !common::admin_get(&admin, "/metrics").await.contains(&format!("rb_backend_ejections_total{{backend=\"{}\"}} 2", dead)) {
+        i += 1;
+        common::served_from(&format!("127.0.1.{}", i % 250 + 1), balancer.addr).await;
+    }
+    balancer.wait_for_line(&format!("Ejecting backend {} for 600ms", dead)).await;
+}
+
+#[tokio::test]
+async fn ejected_backend_is_reinstated_after_successful_trial() {
+    let live = common::spawn_backend(b"pong", Duration::ZERO).await;
+    // Adresse dédiée : les ports éphémères des clients ne peuvent pas la reprendre
+    let flaky = std::net::TcpListener::bind("127.0.0.200:0").unwrap().local_addr().unwrap();
+    let mut balancer = common::spawn_balancer(&[
+        "--server", &live.to_string(),
+        "--server", &flaky.to_string(),
+        "--outlier-detection", "consecutive=1,base_ejection=0.2",
+        "--admin", "127.0.0.1:0",
+    ])
+    .await;
+    let admin = balancer.wait_for_line("Admin interface running on ").await;
+
+    // Provoque l'éjection du serveur arrêté
+    while !common::admin_get(&admin, "/metrics").await.contains(&format!("rb_backend_ejected{{backend=\"{}\"}} 1", flaky)) {
+        common::served_from("127.0.0.1", balancer.addr).await;
+    }
+
+    // Le serveur redémarre : l'essai suivant l'éjection réussit et il est réintégré
+    let listener = TcpListener::bind(flaky).await.unwrap();
+    tokio::spawn(async move {
+        while let Ok((socket, _)) = listener.accept().await {
+            drop(socket);
+        }
+    });
+    tokio::time::sleep(Duration::from_millis(250)).await;
+    while !common::admin_get(&admin, "/metrics").await.contains(&format!("rb_backend_ejected{{backend=\"{}\"}} 0", flaky)) {
+        common::served_from("127.0.0.1", balancer.addr).await;
+    }
+    balancer.wait_for_line(&format!("Reinstating backend {}", flaky)).await;
+}
+
+#[tokio::test]
+async fn ejections_never_exceed_the_pool_cap() {
+    let (first, second) = (unused_addr(), unused_addr());
+    let mut balancer = common::spawn_balancer(&[
+        "--server", &first.to_string(),
+        "--server", &second.to_string(),
+        "--outlier-detection", "consecutive=1,max_ejected=50",
+        "--admin", "127.0.0.1:0",
+    ])
+    .await;
+    let admin = balancer.wait_for_line("Admin interface running on ").await;
+
+    for _ in 0..10 {
+        common::served_from("127.0.0.1", balancer.addr).await;
+    }
+    let metrics = common::admin_get(&admin, "/metrics").await;
+    let ejected = metrics.lines().filter(|l| l.starts_with("rb_backend_ejected{") && l.ends_with(" 1")).count();
+    assert_eq!(ejected, 1, "{}", metrics);
+}
+
+#[tokio::test]
+async fn clients_do_not_queue_when_every_backend_is_ejected() {
+    let mut harness = Harness::start(1, &["--outlier-detection", "consecutive=1,base_ejection=10,max_ejected=100", "--queue-size", "5", "--queue-timeout", "30"]).await;
+    harness.kill(0).await;
+    assert_eq!(harness.request("127.0.7.1").await, None);
+
+    // Le seul serveur est éjecté : la connexion est fermée tout de suite au lieu d'attendre
+    // une place pendant toute la durée de la file
+    assert_eq!(harness.request("127.0.7.2").await, None);
+    let metrics = harness.balancer.metrics();
+    assert_eq!(metrics.closed(Termination::NoBackend), 1);
+    assert_eq!(metrics.closed(Termination::QueueTimeout), 0);
+
+    // À la fin de l'éjection, le serveur rétabli reçoit la connexion d'essai
+    harness.revive(0).await;
+    harness.clock.advance(Duration::from_secs(10));
+    assert_eq!(harness.request("127.0.7.3").await, Some(0));
+    assert!(harness.shutdown().await);
+}
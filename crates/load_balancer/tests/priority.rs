@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+use rb_test_utils as common;
+
+#[tokio::test]
+async fn backup_only_serves_when_primaries_lack_capacity() {
+    let primary = common::spawn_backend(b"pong", Duration::ZERO).await;
+    let backup = common::spawn_backend(b"pang", Duration::ZERO).await;
+    let balancer = common::spawn_balancer(&[
+        "--server", &format!("{},max_conns=1", primary),
+        "--server", &format!("{},priority=1", backup),
+        "--failover-threshold", "100",
+    ])
+    .await;
+
+    // Le serveur principal a de la place : le secours ne reçoit rien
+    for i in 1..=5 {
+        assert!(common::served_from(&format!("127.0.4.{}", i), balancer.addr).await.0);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    // Le serveur principal est plein : le trafic bascule vers le secours
+    let (served, held) = common::served_from("127.0.4.100", balancer.addr).await;
+    assert!(served);
+    assert!(!common::served_from("127.0.4.101", balancer.addr).await.0);
+
+    // Il revient vers le serveur principal dès qu'une place se libère
+    drop(held);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(common::served_from("127.0.4.102", balancer.addr).await.0);
+}
+
+// Bascule un client vers le secours, libère le serveur principal puis indique si le même
+// client revient sur le serveur principal
+async fn returns_to_primary(failback: &str) -> bool {
+    let primary = common::spawn_backend(b"pong", Duration::ZERO).await;
+    let backup = common::spawn_backend(b"pang", Duration::ZERO).await;
+    let balancer = common::spawn_balancer(&[
+        "--server", &format!("{},max_conns=1", primary),
+        "--server", &format!("{},priority=1", backup),
+        "--failover-threshold", "100",
+        "--failback", failback,
+    ])
+    .await;
+
+    let (_, held) = common::served_from("127.0.5.1", balancer.addr).await;
+    assert!(!common::served_from("127.0.5.2", balancer.addr).await.0);
+    drop(held);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    common::served_from("127.0.5.2", balancer.addr).await.0
+}
+
+#[tokio::test]
+async fn failback_keeps_or_drops_affinity_to_backup() {
+    assert!(!returns_to_primary("sticky").await);
+    assert!(returns_to_primary("immediate").await);
+}
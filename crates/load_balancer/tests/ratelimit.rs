@@ -0,0 +1,45 @@
+use std::time::Duration;
+
+use rb_test_utils as common;
+
+#[tokio::test]
+async fn ip_rate_limits_new_connections() {
+    let backend = common::spawn_backend(b"pong", Duration::ZERO).await;
+    let mut balancer = common::spawn_balancer(&["--server", &backend.to_string(), "--ip-rate", "1,burst=2", "--admin", "127.0.0.1:0"]).await;
+    let admin = balancer.wait_for_line("Admin interface running on ").await;
+
+    assert!(common::served_from("127.0.0.1", balancer.addr).await.0);
+    assert!(common::served_from("127.0.0.1", balancer.addr).await.0);
+    assert!(!common::served_from("127.0.0.1", balancer.addr).await.0, "burst exceeded but connection was served");
+
+    // Une autre adresse a son propre seau
+    assert!(common::served_from("127.0.0.2", balancer.addr).await.0);
+    assert!(common::admin_get(&admin, "/metrics").await.contains("rb_connections_closed_total{reason=\"rate_limited\"} 1"));
+}
+
+#[tokio::test]
+async fn cidr_rate_groups_clients_of_the_same_network() {
+    let backend = common::spawn_backend(b"pong", Duration::ZERO).await;
+    let balancer = common::spawn_balancer(&["--server", &backend.to_string(), "--cidr-rate", "0.1,burst=1,v4=8"]).await;
+
+    assert!(common::served_from("127.0.0.1", balancer.addr).await.0);
+    assert!(!common::served_from("127.0.0.2", balancer.addr).await.0, "same /8 network should share the bucket");
+}
+
+#[tokio::test]
+async fn concurrent_connections_per_ip_are_capped() {
+    let backend = common::spawn_backend(b"pong", Duration::ZERO).await;
+    let mut balancer = common::spawn_balancer(&["--server", &backend.to_string(), "--max-conns-per-ip", "1", "--admin", "127.0.0.1:0"]).await;
+    let admin = balancer.wait_for_line("Admin interface running on ").await;
+
+    let (served, first) = common::served_from("127.0.0.1", balancer.addr).await;
+    assert!(served);
+    assert!(!common::served_from("127.0.0.1", balancer.addr).await.0);
+    assert!(common::served_from("127.0.0.2", balancer.addr).await.0);
+    assert!(common::admin_get(&admin, "/metrics").await.contains("rb_connections_closed_total{reason=\"ip_max_connections\"} 1"));
+
+    // La place se libère à la fin de la première connexion
+    drop(first);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(common::served_from("127.0.0.1", balancer.addr).await.0);
+}
@@ -0,0 +1,50 @@
+#![cfg(unix)]
+
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use rb_test_utils as common;
+
+#[tokio::test]
+async fn in_flight_connection_completes_after_sigterm() {
+    let backend = common::spawn_backend(b"pong", Duration::from_millis(500)).await;
+    let mut balancer = common::spawn_balancer(&["--server", &backend.to_string(), "--drain-timeout", "5"]).await;
+
+    // Démarre un échange qui sera encore en cours à la réception du signal
+    let mut client = TcpStream::connect(balancer.addr).await.unwrap();
+    client.write_all(b"ping").await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    common::send_signal(balancer.child.id().unwrap(), "TERM");
+
+    // La réponse arrive malgré l'arrêt
+    let mut buf = [0; 16];
+    let n = client.read(&mut buf).await.unwrap();
+    assert_eq!(&buf[..n], b"pong");
+    drop(client);
+
+    // Le listener est fermé dès la réception du signal
+    assert!(TcpStream::connect(balancer.addr).await.is_err(), "new connections should be refused while draining");
+
+    let status = balancer.child.wait().await.unwrap();
+    assert_eq!(status.code(), Some(0));
+}
+
+#[tokio::test]
+async fn drain_timeout_closes_remaining_connections() {
+    let backend = common::spawn_backend(b"pong", Duration::from_secs(30)).await;
+    let mut balancer = common::spawn_balancer(&["--server", &backend.to_string(), "--drain-timeout", "0.5"]).await;
+
+    let mut client = TcpStream::connect(balancer.addr).await.unwrap();
+    client.write_all(b"ping").await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    common::send_signal(balancer.child.id().unwrap(), "INT");
+
+    // La connexion est fermée sans réponse une fois le délai expiré
+    let mut buf = [0; 16];
+    let read = tokio::time::timeout(Duration::from_secs(5), client.read(&mut buf)).await.unwrap();
+    assert!(matches!(read, Ok(0) | Err(_)));
+
+    let status = tokio::time::timeout(Duration::from_secs(5), balancer.child.wait()).await.unwrap().unwrap();
+    assert_eq!(status.code(), Some(3));
+}
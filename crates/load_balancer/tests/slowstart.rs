@@ -0,0 +1,98 @@
+use std::time::Duration;
+use tokio::net::TcpListener;
+
+use rb_test_utils as common;
+
+// Valeur d'une métrique d'un serveur cible dans la sortie de `/metrics`
+fn backend_metric(metrics: &str, name: &str, backend: &str) -> f64 {
+    let prefix = format!("{}{{backend=\"{}\"}} ", name, backend);
+    metrics.lines().find_map(|l| l.strip_prefix(&prefix)).unwrap().parse().unwrap()
+}
+
+#[tokio::test]
+async fn servers_are_chosen_according_to_their_weight() {
+    let heavy = common::spawn_backend(b"pong", Duration::ZERO).await;
+    let light = common::spawn_backend(b"pang", Duration::ZERO).await;
+    let balancer = common::spawn_balancer(&[
+        "--server", &format!("{},weight=3", heavy),
+        "--server", &light.to_string(),
+    ])
+    .await;
+
+    // Une adresse source différente par connexion pour ne pas dépendre de l'affinité
+    let mut heavy_count = 0;
+    for i in 0..200 {
+        if common::served_from(&format!("127.0.{}.{}", 2 + i / 250, i % 250 + 1), balancer.addr).await.0 {
+            heavy_count += 1;
+        }
+    }
+    assert!((120..=180).contains(&heavy_count), "{} connections on the heavy server", heavy_count);
+}
+
+#[tokio::test]
+async fn reinstated_server_ramps_up_its_weight() {
+    let live = common::spawn_backend(b"pong", Duration::ZERO).await;
+    // Adresse dédiée : les ports éphémères des clients ne peuvent pas la reprendre
+    let flaky = std::net::TcpListener::bind("127.0.0.201:0").unwrap().local_addr().unwrap();
+    let mut balancer = common::spawn_balancer(&[
+        "--server", &format!("{},weight=4", live),
+        "--server", &format!("{},weight=4", flaky),
+        "--outlier-detection", "consecutive=1,base_ejection=0.2",
+        "--slow-start", "1.5,min_weight=0.25",
+        "--admin", "127.0.0.1:0",
+    ])
+    .await;
+    let admin = balancer.wait_for_line("Admin interface running on ").await;
+    let flaky_addr = flaky.to_string();
+
+    // Les serveurs qui viennent d'être ajoutés ont fini leur montée en charge après la fenêtre
+    tokio::time::sleep(Duration::from_millis(1600)).await;
+    let metrics = common::admin_get(&admin, "/metrics").await;
+    assert_eq!(backend_metric(&metrics, "rb_backend_weight", &flaky_addr), 4.0);
+
+    let mut i = 0;
+    while backend_metric(&common::admin_get(&admin, "/metrics").await, "rb_backend_ejected", &flaky_addr) == 0.0 {
+        i += 1;
+        common::served_from(&format!("127.0.3.{}", i % 250 + 1), balancer.addr).await;
+    }
+
+    // Le serveur redémarre et réussit sa connexion d'essai
+    let listener = TcpListener::bind(flaky).await.unwrap();
+    tokio::spawn(async move {
+        while let Ok((socket, _)) = listener.accept().await {
+            drop(socket);
+        }
+    });
+    tokio::time::sleep(Duration::from_millis(250)).await;
+    while backend_metric(&common::admin_get(&admin, "/metrics").await, "rb_backend_ejected", &flaky_addr) == 1.0 {
+        i += 1;
+        common::served_from(&format!("127.0.3.{}", i % 250 + 1), balancer.addr).await;
+    }
+    balancer.wait_for_line(&format!("Reinstating backend {}", flaky)).await;
+
+    // Son poids remonte progressivement, celui de l'autre serveur ne change pas
+    let metrics = common::admin_get(&admin, "/metrics").await;
+    let ramping = backend_metric(&metrics, "rb_backend_weight", &flaky_addr);
+    assert!((1.0..4.0).contains(&ramping), "weight {} during slow start", ramping);
+    assert_eq!(backend_metric(&metrics, "rb_backend_weight", &live.to_string()), 4.0);
+
+    tokio::time::sleep(Duration::from_millis(1600)).await;
+    let metrics = common::admin_get(&admin, "/metrics").await;
+    assert_eq!(backend_metric(&metrics, "rb_backend_weight", &flaky_addr), 4.0);
+}
+
+#[tokio::test]
+async fn new_servers_ramp_up_without_outlier_detection() {
+    let backend = common::spawn_backend(b"pong", Duration::ZERO).await;
+    let mut balancer = common::spawn_balancer(&[
+        "--server", &format!("{},weight=4", backend),
+        "--slow-start", "60,min_weight=0.25",
+        "--admin", "127.0.0.1:0",
+    ])
+    .await;
+    let admin = balancer.wait_for_line("Admin interface running on ").await;
+
+    // Le serveur vient d'être ajouté : son poids part d'un quart de son poids configuré
+    let weight = backend_metric(&common::admin_get(&admin, "/metrics").await, "rb_backend_weight", &backend.to_string());
+    assert!((1.0..1.5).contains(&weight), "weight {} at startup", weight);
+}
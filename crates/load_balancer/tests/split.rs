@@ -0,0 +1,74 @@
+use std::time::Duration;
+
+use rb_test_utils as common;
+use rustic_balancer::Split;
+
+// Compte les connexions servies par le pool qui répond `pong`, une adresse source par connexion
+async fn served_by_blue(addr: std::net::SocketAddr) -> usize {
+    let mut count = 0;
+    for i in 1..=100 {
+        if common::served_from(&format!("127.0.6.{}", i), addr).await.0 {
+            count += 1;
+        }
+    }
+    count
+}
+
+#[tokio::test]
+async fn clients_are_split_between_pools_and_flipped_at_runtime() {
+    let blue = common::spawn_backend(b"pong", Duration::ZERO).await;
+    let green = common::spawn_backend(b"pang", Duration::ZERO).await;
+    let mut balancer = common::spawn_balancer(&[
+        "--server", &format!("{},pool=blue", blue),
+        "--server", &format!("{},pool=green", green),
+        "--split", "blue=80,green=20",
+        "--admin", "127.0.0.1:0",
+    ])
+    .await;
+    let admin = balancer.wait_for_line("Admin interface running on ").await;
+
+    // Chaque client retombe du même côté d'une connexion à l'autre
+    let first = served_by_blue(balancer.addr).await;
+    assert!((60..=95).contains(&first), "{} clients on blue", first);
+    tokio::time::sleep(Duration::from_millis(2100)).await;
+    assert_eq!(served_by_blue(balancer.addr).await, first);
+
+    // La bascule bleu/vert s'applique d'un coup, même aux clients encore en affinité
+    let (status, body) = common::admin_request(&admin, "PUT", "/split?blue=0&green=100").await;
+    assert!(status.contains("200"), "{}", status);
+    assert_eq!(body, "blue=0,green=100\n");
+    assert_eq!(served_by_blue(balancer.addr).await, 0);
+    common::admin_request(&admin, "PUT", "/split?blue=100&green=0").await;
+    assert_eq!(served_by_blue(balancer.addr).await, 100);
+    assert_eq!(common::admin_get(&admin, "/split").await, "blue=100,green=0\n");
+}
+
+#[tokio::test]
+async fn invalid_split_is_refused() {
+    let backend = common::spawn_backend(b"pong", Duration::ZERO).await;
+    let mut balancer = common::spawn_balancer(&["--server", &format!("{},pool=blue", backend), "--admin", "127.0.0.1:0"]).await;
+    let admin = balancer.wait_for_line("Admin interface running on ").await;
+
+    let (status, _) = common::admin_request(&admin, "PUT", "/split?blue=90").await;
+    assert!(status.contains("400"), "{}", status);
+
+    // Des pourcentages dont la somme déborde ne font pas 100 pour autant
+    let (status, body) = common::admin_request(&admin, "PUT", "/split?blue=4294967196&green=200").await;
+    assert!(status.contains("400"), "{}", status);
+    assert_eq!(body, "invalid percentage: 4294967196\n");
+    let (status, body) = common::admin_request(&admin, "PUT", "/split?blue=50&green=50").await;
+    assert!(status.contains("400"), "{}", status);
+    assert_eq!(body, "no server in pool: green\n");
+}
+
+#[test]
+fn clients_keep_their_bucket_across_builds() {
+    // FNV-1a sur les octets de l'adresse : ces tranches ne doivent jamais changer
+    assert_eq!(Split::bucket("192.0.2.1"), 32);
+    assert_eq!(Split::bucket("127.0.6.1"), 27);
+    assert_eq!(Split::bucket("2001:db8::"), 79);
+
+    let split = Split::parse("blue=30,green=70").unwrap();
+    assert_eq!(split.pool_for("127.0.6.1"), "blue");
+    assert_eq!(split.pool_for("192.0.2.1"), "green");
+}
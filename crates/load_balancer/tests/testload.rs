@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+use rustic_balancer::{ManualClock, Pool};
+
+#[tokio::test]
+async fn test_load_balancer() {
+    // Crée un pool partagé sur les serveurs par défaut, avec une horloge avancée à la main
+    let clock = Arc::new(ManualClock::new());
+    let pool = Arc::new(Mutex::new(Pool::new().with_clock(clock.clone())));
+
+    // Teste le choix des serveurs
+    test_cache_functionality(pool.clone(), &clock).await;
+    test_random_server_selection(pool.clone()).await;
+}
+
+async fn test_cache_functionality(pool: Arc<Mutex<Pool>>, clock: &ManualClock) {
+    let mut pool = pool.lock().await;
+
+    // Vérifie que le cache fonctionne correctement
+    let server1 = pool.get_server("127.0.0.1").await.unwrap();
+    let server2 = pool.get_server("127.0.0.1").await.unwrap();
+    assert!(Arc::ptr_eq(&server1, &server2), "Le cache ne fonctionne pas correctement");
+
+    // Vérifie que le cache tient encore juste avant 2 secondes
+    let clients: Vec<String> = (0..20).map(|i| format!("192.168.0.{}", i)).collect();
+    let mut before = Vec::new();
+    for ip in &clients {
+        before.push(pool.get_server(ip).await.unwrap());
+    }
+    clock.advance(Duration::from_millis(1900));
+    for (ip, server1) in clients.iter().zip(&before) {
+        let server2 = pool.get_server(ip).await.unwrap();
+        assert!(Arc::ptr_eq(server1, &server2), "Le cache expire trop tôt");
+    }
+
+    // Vérifie que le cache expire après 2 secondes : les serveurs sont alors tirés à nouveau,
+    // et au moins un client sur vingt change de serveur
+    clock.advance(Duration::from_millis(200));
+    let mut changed = false;
+    for (ip, server1) in clients.iter().zip(&before) {
+        let server3 = pool.get_server(ip).await.unwrap();
+        changed |= !Arc::ptr_eq(server1, &server3);
+    }
+    assert!(changed, "Le cache n'expire pas correctement");
+}
+
+async fn test_random_server_selection(pool: Arc<Mutex<Pool>>) {
+    let mut pool = pool.lock().await;
+    let mut server_counts = HashMap::new();
+
+    // Effectue 100 requêtes depuis des clients différents et compte le nombre de fois que chaque serveur est sélectionné
+    for i in 0..100 {
+        let server = pool.get_server(&format!("10.0.0.{}", i)).await.unwrap();
+        *server_counts.entry(server.addr.clone()).or_insert(0) += 1;
+    }
+
+    // Vérifie que les deux serveurs sont sélectionnés de manière aléatoire
+    assert!(server_counts["127.0.0.1:8080"] > 0, "Le serveur 8080 n'a pas été sélectionné");
+    assert!(server_counts["127.0.0.1:8081"] > 0, "Le serveur 8081 n'a pas été sélectionné");
+}
+
+#[tokio::test]
+async fn expired_affinities_are_forgotten() {
+    let clock = Arc::new(ManualClock::new());
+    let mut pool = Pool::new().with_clock(clock.clone());
+
+    // Des milliers de clients de passage, par exemple des adresses IPv6 temporaires
+    for i in 0..2000 {
+        pool.get_server(&format!("2001:db8::{:x}", i)).await.unwrap();
+    }
+    assert_eq!(pool.affinity_entries(), 2000);
+
+    // Une fois leur affinité expirée, un nettoyage finit par les retirer du cache
+    clock.advance(Duration::from_secs(3));
+    for _ in 0..1024 {
+        pool.get_server("2001:db8::ffff").await.unwrap();
+    }
+    assert_eq!(pool.affinity_entries(), 1);
+}
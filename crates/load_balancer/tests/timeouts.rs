@@ -0,0 +1,172 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use rb_test_utils as common;
+use rustic_balancer::Config;
+
+// Attend que la connexion soit fermée par le load balancer et retourne le délai écoulé
+async fn wait_closed(client: &mut TcpStream) -> Duration {
+    let start = std::time::Instant::now();
+    let mut buf = [0; 16];
+    loop {
+        match tokio::time::timeout(Duration::from_secs(5), client.read(&mut buf)).await.expect("connection was not closed") {
+            Ok(0) | Err(_) => return start.elapsed(),
+            Ok(_) => {}
+        }
+    }
+}
+
+// Attend que le compteur de la raison donnée atteigne `count`
+async fn wait_metric(admin: &str, reason: &str, count: u64) {
+    let line = format!("rb_connections_closed_total{{reason=\"{}\"}} {}", reason, count);
+    for _ in 0..50 {
+        if common::admin_get(admin, "/metrics").await.lines().any(|l| l == line) {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("metric never reached: {}", line);
+}
+
+#[tokio::test]
+async fn silent_client_hits_first_byte_timeout() {
+    let backend = common::spawn_backend(b"pong", Duration::ZERO).await;
+    let mut balancer = common::spawn_balancer(&["--server", &backend.to_string(), "--first-byte-timeout", "0.2", "--admin", "127.0.0.1:0"]).await;
+    let admin = balancer.wait_for_line("Admin interface running on ").await;
+
+    let mut client = TcpStream::connect(balancer.addr).await.unwrap();
+    assert!(wait_closed(&mut client).await >= Duration::from_millis(150));
+    wait_metric(&admin, "first_byte_timeout", 1).await;
+}
+
+#[tokio::test]
+async fn quiet_connection_hits_idle_timeout() {
+    let backend = common::spawn_backend(b"pong", Duration::ZERO).await;
+    let mut balancer = common::spawn_balancer(&["--server", &backend.to_string(), "--idle-timeout", "0.2", "--admin", "127.0.0.1:0"]).await;
+    let admin = balancer.wait_for_line("Admin interface running on ").await;
+
+    // Plusieurs échanges sur la même connexion, puis plus rien
+    let mut client = TcpStream::connect(balancer.addr).await.unwrap();
+    let mut buf = [0; 16];
+    for _ in 0..3 {
+        client.write_all(b"ping").await.unwrap();
+        let n = client.read(&mut buf).await.unwrap();
+        assert_eq!(&buf[..n], b"pong");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    wait_closed(&mut client).await;
+    wait_metric(&admin, "idle_timeout", 1).await;
+}
+
+#[tokio::test]
+async fn busy_connection_hits_max_lifetime() {
+    let backend = common::spawn_backend(b"pong", Duration::ZERO).await;
+    let mut balancer = common::spawn_balancer(&["--server", &backend.to_string(), "--max-lifetime", "0.3", "--admin", "127.0.0.1:0"]).await;
+    let admin = balancer.wait_for_line("Admin interface running on ").await;
+
+    // Le trafic continu n'empêche pas la fermeture
+    let mut client = TcpStream::connect(balancer.addr).await.unwrap();
+    let mut buf = [0; 16];
+    let start = std::time::Instant::now();
+    loop {
+        if client.write_all(b"ping").await.is_err() || matches!(client.read(&mut buf).await, Ok(0) | Err(_)) {
+            break;
+        }
+        assert!(start.elapsed() < Duration::from_secs(5), "connection outlived its maximum lifetime");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    wait_metric(&admin, "lifetime_exceeded", 1).await;
+}
+
+#[tokio::test]
+async fn unreachable_backend_is_a_connect_error() {
+    // Réserve un port puis le libère pour qu'aucun serveur n'y écoute
+    let unused = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+    let mut balancer = common::spawn_balancer(&["--server", &unused.to_string(), "--admin", "127.0.0.1:0"]).await;
+    let admin = balancer.wait_for_line("Admin interface running on ").await;
+
+    let mut client = TcpStream::connect(balancer.addr).await.unwrap();
+    wait_closed(&mut client).await;
+    wait_metric(&admin, "connect_error", 1).await;
+    assert!(common::admin_get(&admin, "/metrics").await.contains("rb_connections_accepted_total 1"));
+}
+
+// Démarre un serveur cible qui, dès le premier message, envoie des données sans fin
+async fn spawn_flooding_backend() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut buf = [0; 16];
+                if socket.read(&mut buf).await.is_ok() {
+                    let chunk = vec![b'x'; 64 * 1024];
+                    while socket.write_all(&chunk).await.is_ok() {}
+                }
+            });
+        }
+    });
+    addr
+}
+
+// Démarre un serveur cible qui renvoie tout ce qu'il reçoit
+async fn spawn_echo_backend() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let (mut read, mut write) = socket.split();
+                let _ = tokio::io::copy(&mut read, &mut write).await;
+                let _ = write.shutdown().await;
+            });
+        }
+    });
+    addr
+}
+
+#[tokio::test]
+async fn client_that_stops_reading_hits_idle_timeout() {
+    let backend = spawn_flooding_backend().await;
+    let mut balancer = common::spawn_balancer(&["--server", &backend.to_string(), "--idle-timeout", "0.3", "--admin", "127.0.0.1:0"]).await;
+    let admin = balancer.wait_for_line("Admin interface running on ").await;
+
+    // Le client ne lit jamais la réponse : le relais reste bloqué en écriture vers lui
+    let mut client = TcpStream::connect(balancer.addr).await.unwrap();
+    client.write_all(b"ping").await.unwrap();
+    wait_metric(&admin, "idle_timeout", 1).await;
+}
+
+#[tokio::test]
+async fn large_echo_does_not_deadlock() {
+    const SIZE: usize = 64 * 1024 * 1024;
+    let backend = spawn_echo_backend().await;
+    let balancer = common::spawn_balancer(&["--server", &backend.to_string()]).await;
+
+    // Le client envoie tout avant de lire : chaque sens doit avancer sans attendre l'autre
+    let client = TcpStream::connect(balancer.addr).await.unwrap();
+    let (mut read, mut write) = client.into_split();
+    let writer = tokio::spawn(async move {
+        write.write_all(&vec![b'x'; SIZE]).await.unwrap();
+        write.shutdown().await.unwrap();
+    });
+    let mut echoed = Vec::new();
+    tokio::time::timeout(Duration::from_secs(10), read.read_to_end(&mut echoed)).await.expect("relay deadlocked").unwrap();
+    writer.await.unwrap();
+    assert_eq!(echoed.len(), SIZE);
+}
+
+#[test]
+fn out_of_range_durations_are_refused() {
+    for (option, value) in [
+        ("--drain-timeout", "1e30"),
+        ("--idle-timeout", "1e30"),
+        ("--queue-timeout", "1e300"),
+        ("--outlier-detection", "base_ejection=1e30"),
+        ("--slow-start", "1e30"),
+    ] {
+        assert!(Config::parse([option.to_string(), value.to_string()]).is_err(), "{} {}", option, value);
+    }
+}
@@ -0,0 +1,127 @@
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::process::Command;
+
+use rb_test_utils as common;
+use rustic_balancer::{Address, Balancer, Config, Listener};
+
+// Répertoire propre à un test, pour ne pas mélanger les sockets des tests lancés en parallèle
+fn socket_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rb-unix-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// Démarre un serveur cible sur un socket Unix, qui répond `reply` à chaque message
+fn spawn_unix_backend(path: &Path, reply: &'static [u8]) {
+    let listener = UnixListener::bind(path).unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            tokio::spawn(async move {
+                let mut buf = [0; 1024];
+                while let Ok(n) = socket.read(&mut buf).await {
+                    if n == 0 || socket.write_all(reply).await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    });
+}
+
+// Umask d'un processus d'après `/proc`, s'il est lisible
+fn umask_of(pid: &str) -> Option<String> {
+    let status = std::fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    status.lines().find_map(|line| line.strip_prefix("Umask:")).map(|umask| umask.trim().to_string())
+}
+
+// Envoie `ping` sur le socket Unix du load balancer et retourne la réponse
+async fn request(path: &Path) -> String {
+    let mut client = UnixStream::connect(path).await.unwrap();
+    client.write_all(b"ping").await.unwrap();
+    let mut buf = [0; 64];
+    let n = tokio::time::timeout(Duration::from_secs(5), client.read(&mut buf)).await.unwrap().unwrap();
+    String::from_utf8_lossy(&buf[..n]).into_owned()
+}
+
+#[tokio::test]
+async fn relays_between_unix_sockets_and_tcp() {
+    let dir = socket_dir("relay");
+    let backend = dir.join("backend.sock");
+    spawn_unix_backend(&backend, b"from unix");
+    let tcp_backend = common::spawn_backend(b"from tcp", Duration::ZERO).await;
+
+    // Un socket laissé par un processus arrêté est remplacé au démarrage
+    let listen = dir.join("lb.sock");
+    drop(std::os::unix::net::UnixListener::bind(&listen).unwrap());
+    assert!(listen.exists());
+
+    let unix_config = Config::parse(["--listen".to_string(), format!("unix:{}", listen.display()), "--server".to_string(), format!("unix:{}", backend.display())]).unwrap();
+    let listener = Listener::bind(&unix_config.listen, 2).await.unwrap();
+    assert_eq!(listener.local_addr().unwrap(), Address::Unix(listen.clone()));
+    tokio::spawn(Balancer::new(unix_config).unwrap().run(listener, std::future::pending()));
+    assert_eq!(request(&listen).await, "from unix");
+
+    // Un client Unix peut aussi être relayé vers un serveur TCP
+    let tcp_listen = dir.join("tcp.sock");
+    let tcp_config = Config::parse(["--listen".to_string(), format!("unix:{}", tcp_listen.display()), "--server".to_string(), tcp_backend.to_string()]).unwrap();
+    let listener = Listener::bind(&tcp_config.listen, 1).await.unwrap();
+    tokio::spawn(Balancer::new(tcp_config).unwrap().run(listener, std::future::pending()));
+    assert_eq!(request(&tcp_listen).await, "from tcp");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn unix_mode_is_applied_when_the_socket_is_created() {
+    let dir = socket_dir("mode");
+    let listen = dir.join("lb.sock");
+    let mut child = Command::new(common::balancer_bin())
+        .args(["--listen", &format!("unix:{}", listen.display()), "--unix-mode", "600"])
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .unwrap();
+
+    // Le socket a ses droits dès que le load balancer annonce son adresse
+    let mut lines = BufReader::new(child.stdout.take().unwrap()).lines();
+    let line = lines.next_line().await.unwrap().unwrap();
+    assert_eq!(line, format!("Load balancer running on unix:{}", listen.display()));
+    assert_eq!(std::fs::metadata(&listen).unwrap().permissions().mode() & 0o777, 0o600);
+
+    // L'umask du processus est rétabli pour les fichiers créés ensuite
+    assert_eq!(umask_of(&child.id().unwrap().to_string()), umask_of("self"));
+    child.kill().await.unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn bind_keeps_live_sockets_and_other_files() {
+    let dir = socket_dir("bind");
+
+    // Un socket encore écouté par un autre processus n'est pas supprimé
+    let live = dir.join("live.sock");
+    let _other = std::os::unix::net::UnixListener::bind(&live).unwrap();
+    let err = Listener::bind(&format!("unix:{}", live.display()), 1).await.err().unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::AddrInUse);
+
+    // Un fichier ordinaire non plus
+    let file = dir.join("data.txt");
+    std::fs::write(&file, "keep me").unwrap();
+    assert!(Listener::bind(&format!("unix:{}", file.display()), 1).await.is_err());
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), "keep me");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn admin_interface_refuses_unix_sockets() {
+    let err = Config::parse(["--admin".to_string(), "unix:/run/rb-admin.sock".to_string()]).err().unwrap();
+    assert!(err.contains("unix sockets"), "{}", err);
+}
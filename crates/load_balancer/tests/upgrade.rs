@@ -0,0 +1,89 @@
+#![cfg(unix)]
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use rb_test_utils as common;
+
+// Arrête le processus successeur, qui n'est pas un enfant du test
+struct KillOnDrop(u32);
+
+impl Drop for KillOnDrop {
+    fn drop(&mut self) {
+        common::send_signal(self.0, "KILL");
+    }
+}
+
+#[tokio::test]
+async fn sigusr2_hands_listener_to_successor_without_refusing_connections() {
+    let backend = common::spawn_backend(b"pong", Duration::ZERO).await;
+    let mut balancer = common::spawn_balancer(&["--server", &backend.to_string(), "--drain-timeout", "5"]).await;
+    let addr = balancer.addr;
+
+    // Envoie des requêtes en continu pendant toute la mise à jour
+    let stop = Arc::new(AtomicBool::new(false));
+    let served = Arc::new(AtomicUsize::new(0));
+    let client = tokio::spawn({
+        let stop = stop.clone();
+        let served = served.clone();
+        async move {
+            while !stop.load(Ordering::Relaxed) {
+                let mut socket = TcpStream::connect(addr).await.expect("connection refused during upgrade");
+                socket.write_all(b"ping").await.unwrap();
+                let mut buf = [0; 16];
+                let n = socket.read(&mut buf).await.unwrap();
+                assert_eq!(&buf[..n], b"pong");
+                served.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    });
+
+    common::send_signal(balancer.child.id().unwrap(), "USR2");
+    let pid: u32 = balancer.wait_for_line("Upgrade requested, started successor with pid ").await.parse().unwrap();
+    let _successor = KillOnDrop(pid);
+
+    // L'ancien processus s'arrête proprement une fois le successeur prêt
+    let status = tokio::time::timeout(Duration::from_secs(10), balancer.child.wait()).await.unwrap().unwrap();
+    assert_eq!(status.code(), Some(0));
+
+    // Le successeur continue de servir sur la même adresse
+    let before = served.load(Ordering::Relaxed);
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    stop.store(true, Ordering::Relaxed);
+    client.await.unwrap();
+    assert!(served.load(Ordering::Relaxed) > before, "successor is not serving connections");
+}
+
+#[tokio::test]
+async fn second_sigusr2_is_ignored_while_a_successor_is_starting() {
+    // Le fichier de règles est un tube : chaque processus reste bloqué à son démarrage tant
+    // que le test n'y a pas écrit, ce qui garde le successeur en cours de démarrage
+    let dir = std::env::temp_dir().join(format!("rb-upgrade-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let fifo = dir.join("acl.fifo");
+    let _ = std::fs::remove_file(&fifo);
+    assert!(std::process::Command::new("mkfifo").arg(&fifo).status().unwrap().success());
+    let feed = |fifo: std::path::PathBuf| std::thread::spawn(move || std::fs::write(fifo, "deny 192.0.2.1\n").unwrap());
+
+    let backend = common::spawn_backend(b"pong", Duration::ZERO).await;
+    feed(fifo.clone());
+    let mut balancer = common::spawn_balancer(&["--server", &backend.to_string(), "--acl-file", fifo.to_str().unwrap(), "--acl-reload", "3600"]).await;
+
+    common::send_signal(balancer.child.id().unwrap(), "USR2");
+    let pid: u32 = balancer.wait_for_line("Upgrade requested, started successor with pid ").await.parse().unwrap();
+    let _successor = KillOnDrop(pid);
+
+    // Le successeur n'a pas encore pris la main : une nouvelle demande n'en lance pas un second
+    common::send_signal(balancer.child.id().unwrap(), "USR2");
+    let line = tokio::time::timeout(Duration::from_secs(5), balancer.wait_for_line("Upgrade ")).await.unwrap();
+    assert_eq!(line, format!("already in progress with successor pid {}, ignoring", pid));
+
+    // Une fois prêt, le successeur arrête l'ancien processus
+    feed(fifo.clone());
+    let status = tokio::time::timeout(Duration::from_secs(10), balancer.child.wait()).await.unwrap().unwrap();
+    assert_eq!(status.code(), Some(0));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,68 @@
+#![cfg(unix)]
+
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use rb_test_utils as common;
+
+#[tokio::test]
+async fn reuseport_workers_serve_and_drain() {
+    let backend = common::spawn_backend(b"pong", Duration::ZERO).await;
+    let mut balancer = common::spawn_balancer(&["--server", &backend.to_string(), "--workers", "4", "--pin-cpus"]).await;
+
+    // Ouvre assez de connexions pour qu'elles soient réparties sur plusieurs workers
+    let mut clients = Vec::new();
+    for _ in 0..32 {
+        let addr = balancer.addr;
+        clients.push(tokio::spawn(async move {
+            let mut socket = TcpStream::connect(addr).await.unwrap();
+            socket.write_all(b"ping").await.unwrap();
+            let mut buf = [0; 16];
+            let n = socket.read(&mut buf).await.unwrap();
+            assert_eq!(&buf[..n], b"pong");
+        }));
+    }
+    for client in clients {
+        client.await.unwrap();
+    }
+
+    // Chaque worker s'arrête et draine à la réception du signal
+    common::send_signal(balancer.child.id().unwrap(), "TERM");
+    for _ in 0..4 {
+        balancer.wait_for_line("Shutdown requested").await;
+    }
+    let status = tokio::time::timeout(Duration::from_secs(5), balancer.child.wait()).await.unwrap().unwrap();
+    assert_eq!(status.code(), Some(0));
+}
+
+// Dernier CPU que le processus de test a le droit d'utiliser
+#[cfg(target_os = "linux")]
+fn last_allowed_cpu() -> String {
+    let status = std::fs::read_to_string("/proc/self/status").unwrap();
+    let list = status.lines().find_map(|line| line.strip_prefix("Cpus_allowed_list:")).unwrap();
+    list.trim().rsplit([',', '-']).next().unwrap().to_string()
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn workers_are_pinned_within_the_allowed_cpus() {
+    let backend = common::spawn_backend(b"pong", Duration::ZERO).await;
+
+    // Le processus n'a droit qu'à son dernier CPU, qui n'est pas forcément le CPU 0
+    let mut command = tokio::process::Command::new("taskset");
+    let bin = common::balancer_bin();
+    command.args(["-c", &last_allowed_cpu(), bin.to_str().unwrap(), "--listen", "127.0.0.1:0", "--server", &backend.to_string(), "--workers", "4", "--pin-cpus"]);
+    let mut balancer = common::spawn_balancer_command(command).await;
+    let mut socket = TcpStream::connect(balancer.addr).await.unwrap();
+    socket.write_all(b"ping").await.unwrap();
+    let mut buf = [0; 16];
+    assert_eq!(socket.read(&mut buf).await.unwrap(), 4);
+    drop(socket);
+
+    common::send_signal(balancer.child.id().unwrap(), "TERM");
+    tokio::time::timeout(Duration::from_secs(5), balancer.child.wait()).await.unwrap().unwrap();
+    while let Some(line) = balancer.output.recv().await {
+        assert!(!line.starts_with("Failed to pin"), "{}", line);
+    }
+}
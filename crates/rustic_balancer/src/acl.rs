@@ -0,0 +1,127 @@
+//! Listes d'adresses autorisées et refusées, évaluées à l'acceptation des connexions.
+//!
+//! Une adresse refusée par une règle `deny` est toujours rejetée. Si au moins une règle
+//! `allow` existe, seules les adresses qu'elle couvre sont acceptées.
+//!
+//! Les règles viennent de la ligne de commande et, éventuellement, d'un fichier relu dès
+//! qu'il est modifié. Chaque ligne du fichier contient `allow <cidr>` ou `deny <cidr>` ;
+//! les lignes vides et celles qui commencent par `#` sont ignorées.
+
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+
+use crate::cidr::Cidr;
+
+/// Ensemble de règles d'accès.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Rules {
+    pub allow: Vec<Cidr>,
+    pub deny: Vec<Cidr>,
+}
+
+impl Rules {
+    /// Indique si aucune règle n'est définie.
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+
+    /// Indique si une connexion depuis `ip` est autorisée.
+    pub fn permits(&self, ip: IpAddr) -> bool {
+        if self.deny.iter().any(|cidr| cidr.contains(ip)) {
+            return false;
+        }
+        self.allow.is_empty() || self.allow.iter().any(|cidr| cidr.contains(ip))
+    }
+
+    /// Lit les règles d'un fichier.
+    ///
+    /// # Errors
+    ///
+    /// Retourne un message d'erreur si le fichier ne peut pas être lu ou si une ligne est invalide.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let mut rules = Rules::default();
+        for (number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |e: String| format!("{}:{}: {}", path.display(), number + 1, e);
+            match line.split_once(char::is_whitespace) {
+                Some(("allow", cidr)) => rules.allow.push(cidr.parse().map_err(error)?),
+                Some(("deny", cidr)) => rules.deny.push(cidr.parse().map_err(error)?),
+                _ => return Err(error(format!("expected `allow <cidr>` or `deny <cidr>`, got `{}`", line))),
+            }
+        }
+        Ok(rules)
+    }
+
+    // Ajoute les règles d'un autre ensemble à la suite de celles-ci
+    fn extend(&mut self, other: &Rules) {
+        self.allow.extend_from_slice(&other.allow);
+        self.deny.extend_from_slice(&other.deny);
+    }
+}
+
+/// Règles d'accès en vigueur, partagées par tous les workers.
+pub struct AccessList {
+    // Règles données sur la ligne de commande
+    fixed: Rules,
+    // Fichier de règles relu à chaque modification
+    file: Option<PathBuf>,
+    current: RwLock<Arc<Rules>>,
+}
+
+impl AccessList {
+    /// Combine les règles de la ligne de commande avec celles du fichier, s'il y en a un.
+    ///
+    /// # Errors
+    ///
+    /// Retourne un message d'erreur si le fichier ne peut pas être lu ou contient une règle invalide.
+    pub fn new(fixed: Rules, file: Option<PathBuf>) -> Result<Self, String> {
+        let mut rules = fixed.clone();
+        if let Some(file) = &file {
+            rules.extend(&Rules::load(file)?);
+        }
+        Ok(Self { fixed, file, current: RwLock::new(Arc::new(rules)) })
+    }
+
+    /// Indique si une connexion depuis `ip` est autorisée par les règles en vigueur.
+    pub fn permits(&self, ip: IpAddr) -> bool {
+        let rules = Arc::clone(&self.current.read().unwrap());
+        rules.permits(ip)
+    }
+
+    /// Relit le fichier de règles toutes les `interval` tant qu'il est modifié.
+    ///
+    /// Une version invalide du fichier est signalée et les règles précédentes restent en vigueur.
+    pub async fn watch(self: Arc<Self>, interval: Duration) {
+        let Some(file) = self.file.clone() else { return };
+        let mut last_modified = modified(&file);
+        loop {
+            tokio::time::sleep(interval).await;
+            let modified = modified(&file);
+            if modified == last_modified {
+                continue;
+            }
+            last_modified = modified;
+
+            match Rules::load(&file) {
+                Ok(loaded) => {
+                    let mut rules = self.fixed.clone();
+                    rules.extend(&loaded);
+                    println!("Reloaded access list from {}: {} allow, {} deny", file.display(), rules.allow.len(), rules.deny.len());
+                    *self.current.write().unwrap() = Arc::new(rules);
+                }
+                Err(e) => eprintln!("Keeping previous access list, failed to reload {}", e),
+            }
+        }
+    }
+}
+
+// Date de modification du fichier, `None` s'il est inaccessible
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
@@ -0,0 +1,117 @@
+//! Interface d'administration HTTP minimale.
+//!
+//! Routes disponibles :
+//!
+//! * `GET /metrics` - compteurs au format texte Prometheus.
+//! * `GET /split` - répartition actuelle des clients entre pools.
+//! * `PUT /split?<pool>=<pourcentage>&...` - remplace la répartition d'un seul coup, par exemple
+//!   `PUT /split?blue=0&green=100` pour une bascule bleu/vert.
+//! * `GET /faults` - fautes injectées, une ligne par pool.
+//! * `PUT /faults/<pool>?<fautes>` - remplace les fautes d'un pool, par exemple
+//!   `PUT /faults/default?abort=503&percent=10`.
+//! * `DELETE /faults/<pool>` - arrête l'injection de fautes dans un pool.
+
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::balancer::Balancer;
+use crate::fault::Fault;
+use crate::split::Split;
+
+/// Sert l'interface d'administration sur le listener donné.
+pub async fn serve(listener: TcpListener, balancer: Arc<Balancer>) {
+    loop {
+        match listener.accept().await {
+            Ok((socket, _)) => {
+                tokio::spawn(handle(socket, Arc::clone(&balancer)));
+            }
+            Err(e) => eprintln!("Admin interface failed to accept: {}", e),
+        }
+    }
+}
+
+// Répond à une requête d'administration puis ferme la connexion
+async fn handle(mut socket: TcpStream, balancer: Arc<Balancer>) {
+    let mut buf = [0; 4096];
+    let n = match socket.read(&mut buf).await {
+        Ok(n) => n,
+        Err(_) => return,
+    };
+
+    // Seule la ligne de requête est interprétée
+    let request = String::from_utf8_lossy(&buf[..n]);
+    let mut parts = request.lines().next().unwrap_or_default().split_whitespace();
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => {
+            let queued = balancer.queued.load(Ordering::Relaxed);
+            let slow_start = balancer.pool.lock().await.slow_start().copied();
+            let mut body = balancer.metrics.render(&balancer.servers, queued, slow_start.as_ref(), balancer.clock.now());
+            if let Some(mirroring) = &balancer.mirroring {
+                mirroring.render(&mut body);
+            }
+            ("200 OK", body)
+        }
+        (Some("GET"), Some("/split")) => match balancer.pool.lock().await.split() {
+            Some(split) => ("200 OK", format!("{}\n", split)),
+            None => ("404 Not Found", "no split configured\n".to_string()),
+        },
+        (Some("PUT"), Some(path)) if path.starts_with("/split?") => set_split(&balancer, &path["/split?".len()..]).await,
+        (Some("GET"), Some("/faults")) => ("200 OK", balancer.faults.to_string()),
+        (Some("PUT"), Some(path)) if path.starts_with("/faults/") => set_fault(&balancer, &path["/faults/".len()..]),
+        (Some("DELETE"), Some(path)) if path.starts_with("/faults/") => {
+            let pool = &path["/faults/".len()..];
+            if balancer.faults.remove(pool) {
+                println!("Fault injection disabled for pool {}", pool);
+                ("200 OK", String::new())
+            } else {
+                ("404 Not Found", format!("no fault for pool: {}\n", pool))
+            }
+        }
+        _ => ("404 Not Found", "not found\n".to_string()),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    let _ = socket.write_all(response.as_bytes()).await;
+    let _ = socket.shutdown().await;
+}
+
+// Remplace la répartition entre pools, sous le verrou du pool pour que les connexions
+// voient l'ancienne ou la nouvelle répartition, jamais un mélange des deux
+async fn set_split(balancer: &Balancer, query: &str) -> (&'static str, String) {
+    let split = match Split::parse(query) {
+        Ok(split) => split,
+        Err(e) => return ("400 Bad Request", format!("{}\n", e)),
+    };
+    let body = format!("{}\n", split);
+    match balancer.pool.lock().await.set_split(split) {
+        Ok(()) => {
+            println!("Traffic split changed to {}", body.trim_end());
+            ("200 OK", body)
+        }
+        Err(e) => ("400 Bad Request", format!("{}\n", e)),
+    }
+}
+
+// Remplace les fautes injectées dans un pool, décrit par `<pool>?<fautes>`
+fn set_fault(balancer: &Balancer, target: &str) -> (&'static str, String) {
+    let (pool, query) = target.split_once('?').unwrap_or((target, ""));
+    if !balancer.servers.iter().any(|s| s.pool == pool) {
+        return ("400 Bad Request", format!("no server in pool: {}\n", pool));
+    }
+    match Fault::parse(query) {
+        Ok(fault) => {
+            println!("Fault injection for pool {} set to {}", pool, fault);
+            let body = format!("{} {}\n", pool, fault);
+            balancer.faults.set(pool, fault);
+            ("200 OK", body)
+        }
+        Err(e) => ("400 Bad Request", format!("{}\n", e)),
+    }
+}
@@ -0,0 +1,182 @@
+//! Serveurs cibles et suivi de leurs connexions actives.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+use crate::net;
+use crate::outlier::Health;
+use crate::slowstart::SlowStart;
+use crate::throttle::{self, Bandwidth, Throttles};
+
+/// Pool des serveurs déclarés sans option `pool=`.
+pub const DEFAULT_POOL: &str = "default";
+
+/// Serveur cible vers lequel le load balancer relaie les connexions.
+#[derive(Debug)]
+pub struct Backend {
+    /// Adresse du serveur, par exemple `127.0.0.1:8080`, `[::1]:8080` ou `unix:/run/app.sock`.
+    pub addr: String,
+    /// Nombre maximal de connexions simultanées vers ce serveur.
+    pub max_conns: Option<usize>,
+    /// Poids relatif du serveur dans le choix aléatoire.
+    pub weight: u32,
+    /// Niveau de priorité du serveur : `0` pour un serveur principal, plus pour un secours.
+    pub priority: u8,
+    /// Pool du serveur, pour la répartition du trafic entre pools.
+    pub pool: String,
+    /// Débits maximaux vers et depuis ce serveur, toutes connexions confondues.
+    pub bandwidth: Bandwidth,
+    pub(crate) throttles: Throttles,
+    active: AtomicUsize,
+    health: Mutex<Health>,
+    ramp_from: Mutex<Option<Instant>>,
+}
+
+impl Backend {
+    /// Crée un serveur cible principal sans limite de connexions, de poids 1.
+    pub fn new(addr: &str) -> Self {
+        Self {
+            addr: addr.to_string(),
+            max_conns: None,
+            weight: 1,
+            priority: 0,
+            pool: DEFAULT_POOL.to_string(),
+            bandwidth: Bandwidth::default(),
+            throttles: Throttles::default(),
+            active: AtomicUsize::new(0),
+            health: Mutex::new(Health::default()),
+            ramp_from: Mutex::new(None),
+        }
+    }
+
+    /// Lit un serveur cible de la forme `adresse[,option=valeur...]`.
+    ///
+    /// Options reconnues :
+    ///
+    /// * `max_conns=<n>` - nombre maximal de connexions simultanées.
+    /// * `weight=<n>` - poids relatif du serveur (1 par défaut).
+    /// * `priority=<n>` - niveau de priorité, `0` pour un serveur principal (par défaut).
+    /// * `pool=<nom>` - pool du serveur (`default` par défaut).
+    /// * `bandwidth=<débit>` - débit maximal de chaque sens, toutes connexions confondues,
+    ///   voir `Bandwidth::parse` (illimité par défaut).
+    /// * `upload=<débit>` et `download=<débit>` - débit maximal vers ou depuis le serveur.
+    ///
+    /// # Errors
+    ///
+    /// Retourne un message d'erreur si une option est inconnue ou a une valeur invalide.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut parts = spec.split(',');
+        let mut backend = Self::new(parts.next().unwrap_or_default().trim());
+        if backend.addr.is_empty() {
+            return Err(format!("missing server address in: {}", spec));
+        }
+        net::check_addr(&backend.addr)?;
+
+        for option in parts {
+            let (key, value) = option
+                .split_once('=')
+                .ok_or_else(|| format!("invalid server option: {}", option))?;
+            match key.trim() {
+                "max_conns" => {
+                    let max = value.trim().parse().ok().filter(|n| *n > 0);
+                    backend.max_conns = Some(max.ok_or_else(|| format!("invalid max_conns: {}", value))?);
+                }
+                "weight" => {
+                    let weight = value.trim().parse().ok().filter(|n| *n > 0);
+                    backend.weight = weight.ok_or_else(|| format!("invalid weight: {}", value))?;
+                }
+                "priority" => {
+                    backend.priority = value.trim().parse().map_err(|_| format!("invalid priority: {}", value))?;
+                }
+                "pool" => {
+                    if value.trim().is_empty() {
+                        return Err(format!("invalid pool: {}", value));
+                    }
+                    backend.pool = value.trim().to_string();
+                }
+                "bandwidth" => backend.bandwidth = Bandwidth::both(throttle::parse_rate(value)?),
+                "upload" => backend.bandwidth.upload = Some(throttle::parse_rate(value)?),
+                "download" => backend.bandwidth.download = Some(throttle::parse_rate(value)?),
+                _ => return Err(format!("unknown server option: {}", key)),
+            }
+        }
+        backend.throttles = Throttles::new(backend.bandwidth);
+        Ok(backend)
+    }
+
+    /// Nombre de connexions actuellement relayées vers ce serveur.
+    pub fn active(&self) -> usize {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    /// Indique si le serveur peut accepter une connexion de plus.
+    pub fn has_capacity(&self) -> bool {
+        self.max_conns.is_none_or(|max| self.active() < max)
+    }
+
+    /// Indique si le serveur peut être choisi : il a de la place et n'est pas éjecté.
+    pub fn is_available(&self, now: Instant) -> bool {
+        self.has_capacity() && self.health().is_available(now)
+    }
+
+    /// Fait partir la montée en charge du serveur de `now`, à son ajout ou à sa réintégration.
+    pub fn start_ramp(&self, now: Instant) {
+        *self.ramp_from.lock().unwrap() = Some(now);
+    }
+
+    /// Poids effectif du serveur, réduit pendant la montée en charge qui suit son ajout ou sa
+    /// réintégration.
+    pub fn effective_weight(&self, now: Instant, slow_start: Option<&SlowStart>) -> f64 {
+        let weight = f64::from(self.weight);
+        let ramp_from = *self.ramp_from.lock().unwrap();
+        match (slow_start, ramp_from) {
+            (Some(slow_start), Some(at)) => weight * slow_start.factor(now.saturating_duration_since(at)),
+            _ => weight,
+        }
+    }
+
+    /// Santé observée du serveur.
+    pub fn health(&self) -> MutexGuard<'_, Health> {
+        self.health.lock().unwrap()
+    }
+}
+
+/// Connexion comptée sur un serveur cible, libérée à la destruction.
+///
+/// La libération réveille une connexion en attente dans la file, s'il y en a une.
+pub struct BackendGuard {
+    backend: Arc<Backend>,
+    released: Arc<Notify>,
+    trial: bool,
+}
+
+impl BackendGuard {
+    /// Compte une connexion de plus sur `backend`.
+    ///
+    /// L'appelant doit avoir vérifié `is_available` en tenant le verrou du cache, pour que
+    /// deux connexions ne prennent pas la même place.
+    pub fn new(backend: Arc<Backend>, released: Arc<Notify>) -> Self {
+        backend.active.fetch_add(1, Ordering::Relaxed);
+        let trial = backend.health().on_selected();
+        Self { backend, released, trial }
+    }
+
+    /// Indique si la connexion est l'essai qui décide de la réintégration d'un serveur éjecté.
+    pub fn is_trial(&self) -> bool {
+        self.trial
+    }
+
+    /// Le serveur cible de la connexion.
+    pub fn backend(&self) -> &Arc<Backend> {
+        &self.backend
+    }
+}
+
+impl Drop for BackendGuard {
+    fn drop(&mut self) {
+        self.backend.active.fetch_sub(1, Ordering::Relaxed);
+        self.released.notify_one();
+    }
+}
@@ -0,0 +1,535 @@
+//! Prise en charge des connexions : admission, choix du serveur cible, relais et drainage.
+
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+use tokio::sync::{watch, Mutex, Notify, OwnedSemaphorePermit, Semaphore};
+use tokio::task::JoinSet;
+use tokio::time::Instant;
+
+use crate::acl::AccessList;
+use crate::admin;
+use crate::backend::{Backend, BackendGuard};
+use crate::clock::{Clock, SystemClock};
+use crate::config::{Config, Limits};
+use crate::fault::{Fault, Faults};
+use crate::metrics::Metrics;
+use crate::mirror::Mirroring;
+use crate::net::{self, Address, ListenSocket, Stream};
+use crate::outlier::{OutlierDetection, Verdict};
+use crate::pool::{self, Pool};
+use crate::ratelimit::{ClientGuard, ClientLimiter};
+use crate::relay::{self, Shaping, Termination, Timeouts};
+use crate::shutdown;
+use crate::throttle::{Bandwidth, BandwidthLimits, Throttles};
+use crate::worker;
+
+// Attente avant de réessayer d'accepter quand le processus manque de descripteurs ou de mémoire
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Sockets d'écoute du load balancer, un par worker.
+pub struct Listener {
+    listeners: Vec<ListenSocket>,
+}
+
+impl Listener {
+    /// Lie `workers` sockets d'écoute sur `addr`, avec `SO_REUSEPORT` s'il y en a plusieurs.
+    ///
+    /// Une adresse `unix:<chemin>` lie un seul socket Unix, dont les workers se partagent la
+    /// file de connexions. Son fichier est créé avec les droits donnés par l'umask du processus :
+    /// les changer ensuite avec `set_permissions` laisse un court instant où d'autres peuvent s'y
+    /// connecter. Pour qu'il ait ses droits dès sa création, l'appelant règle l'umask autour de
+    /// l'appel, tant qu'aucun autre thread ne crée de fichier, comme le fait `load_balancer`
+    /// avec `--unix-mode`.
+    ///
+    /// # Errors
+    ///
+    /// Retourne une erreur si l'adresse ne peut pas être résolue ou si un socket ne peut pas être lié.
+    pub async fn bind(addr: &str, workers: usize) -> io::Result<Self> {
+        if let Some(path) = net::unix_path(addr) {
+            return Ok(Self { listeners: net::bind_unix(path, workers)? });
+        }
+        let listeners = worker::bind(addr, workers).await?;
+        Ok(Self { listeners: listeners.into_iter().map(ListenSocket::Tcp).collect() })
+    }
+
+    /// Reprend des sockets d'écoute déjà liés, par exemple transmis par un processus précédent.
+    pub fn from_std(listeners: Vec<ListenSocket>) -> Self {
+        Self { listeners }
+    }
+
+    /// Adresse d'écoute.
+    pub fn local_addr(&self) -> io::Result<Address> {
+        self.listeners[0].local_addr()
+    }
+
+    /// Change les droits du fichier d'un socket Unix, par exemple `0o660`, pour des sockets
+    /// repris avec `from_std`. Sans effet en TCP.
+    ///
+    /// # Errors
+    ///
+    /// Retourne une erreur si les droits ne peuvent pas être changés.
+    pub fn set_permissions(&self, mode: u32) -> io::Result<()> {
+        self.listeners[0].set_permissions(mode)
+    }
+
+    /// Copies des sockets d'écoute, pour les transmettre à un successeur.
+    pub fn try_clone(&self) -> io::Result<Vec<ListenSocket>> {
+        self.listeners.iter().map(|l| l.try_clone()).collect()
+    }
+}
+
+/// Load balancer : état partagé par tous les workers et toutes les connexions.
+pub struct Balancer {
+    pub(crate) pool: Mutex<Pool>,
+    pub(crate) servers: Vec<Arc<Backend>>,
+    pub(crate) metrics: Metrics,
+    timeouts: Timeouts,
+    limits: Limits,
+    drain_timeout: Duration,
+    affinity_v6_prefix: u8,
+    worker_runtimes: bool,
+    pin_cpus: bool,
+    // Places disponibles sur le listener, si `--max-connections` est donné
+    connection_slots: Option<Arc<Semaphore>>,
+    // Limites par adresse IP source, si au moins une est configurée
+    clients: Option<Arc<ClientLimiter>>,
+    // Listes d'adresses autorisées et refusées, si des règles ou un fichier sont donnés
+    acl: Option<Arc<AccessList>>,
+    acl_reload: Duration,
+    // Détection passive des serveurs défaillants, si elle est activée
+    outliers: Option<OutlierDetection>,
+    // Copie du trafic vers les serveurs fantômes, si elle est activée
+    pub(crate) mirroring: Option<Arc<Mirroring>>,
+    // Fautes injectées par pool, modifiables depuis l'interface d'administration
+    pub(crate) faults: Faults,
+    // Limites de débit du listener, des clients et des connexions
+    bandwidth: BandwidthLimits,
+    // Réveille la file d'attente quand une connexion vers un serveur se termine
+    released: Arc<Notify>,
+    // Nombre de connexions dans la file d'attente
+    pub(crate) queued: AtomicUsize,
+    // Source du temps pour l'affinité, les limites de débit, les éjections et le démarrage lent
+    pub(crate) clock: Arc<dyn Clock>,
+}
+
+// Places réservées pour une connexion acceptée, libérées à la fin de la connexion
+struct Admission {
+    _slot: Option<OwnedSemaphorePermit>,
+    _client: Option<ClientGuard>,
+}
+
+impl Balancer {
+    /// Crée un load balancer à partir de sa configuration. Les options d'écoute et
+    /// d'administration (`listen`, `workers`, `admin`) sont laissées à l'appelant.
+    ///
+    /// # Errors
+    ///
+    /// Retourne un message d'erreur si le fichier de règles d'accès ne peut pas être chargé.
+    pub fn new(config: Config) -> Result<Arc<Self>, String> {
+        Self::with_clock(config, Arc::new(SystemClock))
+    }
+
+    /// Crée un load balancer dont toute la logique dépendant du temps suit `clock`, par
+    /// exemple une `ManualClock` dans les tests.
+    ///
+    /// # Errors
+    ///
+    /// Retourne un message d'erreur si le fichier de règles d'accès ne peut pas être chargé.
+    pub fn with_clock(config: Config, clock: Arc<dyn Clock>) -> Result<Arc<Self>, String> {
+        // Charge les listes d'accès avant d'accepter la moindre connexion
+        let acl = if config.acl.is_empty() && config.acl_file.is_none() {
+            None
+        } else {
+            let acl = AccessList::new(config.acl, config.acl_file).map_err(|e| format!("Failed to load access list: {}", e))?;
+            Some(Arc::new(acl))
+        };
+
+        let servers: Vec<Arc<Backend>> = config.servers.into_iter().map(Arc::new).collect();
+        let now = clock.now();
+        for server in &servers {
+            server.start_ramp(now);
+        }
+        let pool = Pool::with_servers(servers.clone())
+            .with_slow_start(config.slow_start)
+            .with_failover(config.failover)
+            .with_split(config.split)
+            .with_clock(Arc::clone(&clock));
+        Ok(Arc::new(Self {
+            pool: Mutex::new(pool),
+            servers,
+            metrics: Metrics::default(),
+            timeouts: config.timeouts,
+            limits: config.limits,
+            drain_timeout: config.drain_timeout,
+            affinity_v6_prefix: config.affinity_v6_prefix,
+            worker_runtimes: config.worker_runtimes,
+            pin_cpus: config.pin_cpus,
+            connection_slots: config.limits.max_connections.map(|max| Arc::new(Semaphore::new(max))),
+            clients: config.clients.is_enabled().then(|| Arc::new(ClientLimiter::new(config.clients).with_clock(Arc::clone(&clock)))),
+            acl,
+            acl_reload: config.acl_reload,
+            outliers: config.outliers,
+            mirroring: (!config.mirror_servers.is_empty()).then(|| Arc::new(Mirroring::new(config.mirror_servers, config.mirror_percent))),
+            faults: Faults::new(config.faults),
+            bandwidth: BandwidthLimits::new(config.bandwidth, config.client_bandwidth, config.connection_bandwidth),
+            released: Arc::new(Notify::new()),
+            queued: AtomicUsize::new(0),
+            clock,
+        }))
+    }
+
+    /// Compteurs du load balancer.
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// Fautes injectées dans chaque pool, modifiables à chaud.
+    pub fn faults(&self) -> &Faults {
+        &self.faults
+    }
+
+    /// Sert l'interface d'administration sur le listener donné.
+    pub async fn serve_admin(self: Arc<Self>, listener: TcpListener) {
+        admin::serve(listener, self).await
+    }
+
+    /// Sert les connexions des sockets d'écoute jusqu'à ce que `shutdown` se termine, puis
+    /// draine les connexions en cours.
+    ///
+    /// # Returns
+    ///
+    /// `true` si toutes les connexions se sont terminées avant la fin du délai de drainage.
+    ///
+    /// # Errors
+    ///
+    /// Retourne une erreur, après le drainage, si le socket d'écoute d'un worker devient
+    /// inutilisable. Une erreur d'acceptation propre à une connexion est seulement journalisée.
+    pub async fn run(self: Arc<Self>, listener: Listener, shutdown: impl Future<Output = ()>) -> io::Result<bool> {
+        // La surveillance du fichier de règles s'arrête avec `run` : le JoinSet l'interrompt
+        // quand il est détruit
+        let mut watcher = JoinSet::new();
+        if let Some(acl) = &self.acl {
+            watcher.spawn(Arc::clone(acl).watch(self.acl_reload));
+        }
+
+        // Démarre un worker par socket d'écoute
+        let (stop_tx, stop_rx) = watch::channel(false);
+        let mut workers = JoinSet::new();
+        for (index, listener) in listener.listeners.into_iter().enumerate() {
+            let balancer = Arc::clone(&self);
+            let stop = stop_rx.clone();
+            let drain_timeout = self.drain_timeout;
+            if self.worker_runtimes {
+                workers.spawn(worker::spawn_runtime(index, self.pin_cpus, move || serve(listener, balancer, stop, drain_timeout)));
+            } else {
+                workers.spawn(serve(listener, balancer, stop, drain_timeout));
+            }
+        }
+
+        // Un worker ne s'arrête de lui-même que si son listener devient inutilisable : les
+        // autres sont alors arrêtés et drainés comme pour une demande d'arrêt
+        let mut error = None;
+        tokio::pin!(shutdown);
+        loop {
+            tokio::select! {
+                Some(result) = workers.join_next() => {
+                    if let Err(e) = result.map_err(io::Error::other).and_then(|result| result) {
+                        error = Some(e);
+                        break;
+                    }
+                }
+                _ = &mut shutdown => break,
+            }
+        }
+
+        // Arrête d'accepter immédiatement, puis laisse les connexions en cours se terminer
+        let _ = stop_tx.send(true);
+        let mut drained = true;
+        while let Some(result) = workers.join_next().await {
+            match result.map_err(io::Error::other).and_then(|result| result) {
+                Ok(worker_drained) => drained &= worker_drained,
+                Err(e) => {
+                    error.get_or_insert(e);
+                }
+            }
+        }
+        match error {
+            Some(e) => Err(e),
+            None => Ok(drained),
+        }
+    }
+
+    // Vérifie les limites du listener et du client avant de prendre en charge une connexion
+    fn admit(&self, addr: SocketAddr) -> Result<Admission, Termination> {
+        if self.acl.as_ref().is_some_and(|acl| !acl.permits(addr.ip())) {
+            return Err(Termination::Denied);
+        }
+        let client = match &self.clients {
+            Some(clients) => Some(clients.admit(addr.ip())?),
+            None => None,
+        };
+        let slot = match &self.connection_slots {
+            Some(slots) => Some(Arc::clone(slots).try_acquire_owned().map_err(|_| Termination::MaxConnections)?),
+            None => None,
+        };
+        Ok(Admission { _slot: slot, _client: client })
+    }
+
+    // Enregistre le résultat d'une connexion vers un serveur cible et l'éjecte si nécessaire,
+    // sans dépasser le pourcentage maximal du pool éjecté en même temps
+    fn report(&self, backend: &Arc<Backend>, success: bool) {
+        let Some(detection) = &self.outliers else { return };
+        let now = self.clock.now();
+        let verdict = backend.health().record(success, now, detection);
+        match verdict {
+            Verdict::Healthy => {}
+            Verdict::Reinstated => {
+                println!("Reinstating backend {} after a successful trial", backend.addr);
+                backend.start_ramp(now);
+            }
+            Verdict::ShouldEject => {
+                let others = self.servers.iter().filter(|s| !Arc::ptr_eq(s, backend) && s.health().is_ejected()).count();
+                if (others + 1) * 100 > detection.max_ejected_percent * self.servers.len() {
+                    eprintln!("Not ejecting backend {}: {}% of the pool is already ejected", backend.addr, others * 100 / self.servers.len());
+                    return;
+                }
+                let duration = backend.health().eject(now, detection);
+                eprintln!("Ejecting backend {} for {:?}", backend.addr, duration);
+            }
+        }
+    }
+
+    // Indique si tous les serveurs cibles sont éjectés jusqu'à une date encore à venir
+    fn all_ejected(&self) -> bool {
+        let now = self.clock.now();
+        self.servers.iter().all(|server| server.health().is_open(now))
+    }
+
+    // Réserve une place sur un serveur cible, en attendant dans la file si tous sont pleins
+    async fn acquire_backend(&self, key: &str) -> Result<BackendGuard, Termination> {
+        if let Some(server) = self.pool.lock().await.get_server(key).await {
+            return Ok(BackendGuard::new(server, Arc::clone(&self.released)));
+        }
+
+        // Sans serveur qui ne soit pas éjecté, attendre ne sert à rien : la fin d'une éjection
+        // ne réveille pas la file d'attente
+        if self.all_ejected() {
+            return Err(Termination::NoBackend);
+        }
+
+        // Tous les serveurs sont pleins : entre dans la file d'attente s'il reste de la place
+        if self.queued.fetch_add(1, Ordering::Relaxed) >= self.limits.queue_size {
+            self.queued.fetch_sub(1, Ordering::Relaxed);
+            return Err(Termination::QueueFull);
+        }
+        let deadline = Instant::now().checked_add(self.limits.queue_timeout);
+        let result = loop {
+            // S'inscrit avant de vérifier pour ne pas manquer une libération
+            let released = self.released.notified();
+            tokio::pin!(released);
+            released.as_mut().enable();
+
+            if let Some(server) = self.pool.lock().await.get_server(key).await {
+                break Ok(BackendGuard::new(server, Arc::clone(&self.released)));
+            }
+            if self.all_ejected() {
+                break Err(Termination::NoBackend);
+            }
+            match deadline {
+                Some(deadline) => {
+                    if tokio::time::timeout_at(deadline, released).await.is_err() {
+                        break Err(Termination::QueueTimeout);
+                    }
+                }
+                None => released.await,
+            }
+        };
+        self.queued.fetch_sub(1, Ordering::Relaxed);
+        result
+    }
+}
+
+// Accepte les connexions d'un socket d'écoute jusqu'à la demande d'arrêt, puis draine les
+// connexions en cours. Retourne `true` si elles se sont toutes terminées à temps.
+async fn serve(listener: ListenSocket, balancer: Arc<Balancer>, mut stop: watch::Receiver<bool>, drain_timeout: Duration) -> io::Result<bool> {
+    let listener = listener.into_acceptor()?;
+
+    // Garde la trace des connexions en cours pour pouvoir les drainer à l'arrêt
+    let mut connections = JoinSet::new();
+    let mut error = None;
+
+    // Pause des acceptations après une erreur, sans cesser de surveiller la demande d'arrêt
+    let pause = tokio::time::sleep(Duration::ZERO);
+    tokio::pin!(pause);
+    let mut paused = false;
+
+    // Boucle pour accepter les connexions jusqu'à la demande d'arrêt
+    loop {
+        tokio::select! {
+            // Accepte une nouvelle connexion. `socket` est utilisé pour communiquer avec le client
+            result = listener.accept(), if !paused => {
+                let (socket, addr) = match result {
+                    Ok(accepted) => accepted,
+                    Err(e) => match accept_backoff(&e) {
+                        Some(backoff) => {
+                            eprintln!("Failed to accept connection: {}", e);
+                            pause.as_mut().reset(Instant::now() + backoff);
+                            paused = true;
+                            continue;
+                        }
+                        None => {
+                            eprintln!("Listener failed, no longer accepting connections: {}", e);
+                            error = Some(e);
+                            break;
+                        }
+                    },
+                };
+                balancer.metrics.connection_accepted();
+
+                // Refuse la connexion si le listener ou le client a atteint sa limite
+                let admission = match balancer.admit(addr) {
+                    Ok(admission) => admission,
+                    Err(termination) => {
+                        eprintln!("Rejecting connection from {}: {}", addr, termination);
+                        balancer.metrics.connection_closed(termination);
+                        continue;
+                    }
+                };
+
+                // Crée une nouvelle tâche pour gérer la connexion
+                connections.spawn(handle_connection(socket, addr, Arc::clone(&balancer), admission));
+            }
+            _ = &mut pause, if paused => paused = false,
+            // Libère les tâches des connexions terminées
+            Some(_) = connections.join_next(), if !connections.is_empty() => {}
+            _ = stop.wait_for(|stop| *stop) => break,
+        }
+    }
+
+    // Arrête d'accepter immédiatement, puis laisse les connexions en cours se terminer
+    drop(listener);
+    println!("Shutdown requested, draining {} connection(s)", connections.len());
+    let drained = shutdown::drain(&mut connections, drain_timeout).await;
+    match error {
+        Some(e) => Err(e),
+        None => Ok(drained),
+    }
+}
+
+// Attente avant de réessayer après une erreur d'acceptation, `None` si le listener est
+// inutilisable. Une erreur propre à une connexion est ignorée ; un manque de descripteurs ou
+// de mémoire laisse un peu de temps aux connexions en cours pour en libérer.
+fn accept_backoff(e: &io::Error) -> Option<Duration> {
+    use io::ErrorKind::*;
+
+    if matches!(e.kind(), ConnectionAborted | ConnectionReset | ConnectionRefused | Interrupted | WouldBlock | TimedOut) {
+        return Some(Duration::ZERO);
+    }
+    if e.kind() == OutOfMemory {
+        return Some(ACCEPT_BACKOFF);
+    }
+    #[cfg(unix)]
+    match e.raw_os_error() {
+        Some(libc::EMFILE | libc::ENFILE | libc::ENOBUFS | libc::ENOMEM) => return Some(ACCEPT_BACKOFF),
+        // Erreurs réseau que Linux remonte à `accept` pour la connexion acceptée
+        Some(libc::EPROTO | libc::EPERM | libc::ENETDOWN | libc::ENETUNREACH | libc::EHOSTDOWN | libc::EHOSTUNREACH | libc::ENOPROTOOPT | libc::EOPNOTSUPP) => {
+            return Some(Duration::ZERO)
+        }
+        _ => {}
+    }
+    None
+}
+
+// Relaie une connexion client vers le serveur choisi par le pool. `_admission` garde les
+// places réservées pour la connexion jusqu'à sa fin.
+async fn handle_connection(mut socket: Stream, addr: SocketAddr, balancer: Arc<Balancer>, _admission: Admission) {
+    // Récupère l'adresse IP du client et sa clé d'affinité
+    let ip = addr.ip().to_string();
+    let key = pool::affinity_key(addr.ip(), balancer.affinity_v6_prefix);
+
+    // Obtient le serveur à partir du cache ou choisi un serveur aléatoire,
+    // sans garder le pool verrouillé pendant le relais
+    let start = Instant::now();
+    let guard = match balancer.acquire_backend(&key).await {
+        Ok(guard) => guard,
+        Err(termination) => {
+            eprintln!("Rejecting connection from {}: {} after {:?}", ip, termination, start.elapsed());
+            balancer.metrics.connection_closed(termination);
+            return;
+        }
+    };
+    let server = &guard.backend().addr;
+
+    // Affiche en console l'adresse du client connecté et le serveur cible sélectionné aléatoirement
+    let now = SystemTime::now();
+    println!("Redirecting connection from: {} to {} at {:?}", ip, server, now);
+
+    // Applique les fautes éventuellement injectées dans le pool du serveur cible. La connexion
+    // d'essai d'un serveur éjecté n'en reçoit pas : son issue doit refléter l'état du serveur
+    let fault = if guard.is_trial() { None } else { balancer.faults.get(&guard.backend().pool).filter(Fault::applies) };
+    if let Some(fault) = &fault {
+        eprintln!("Injecting fault into connection from {} to {}: {}", ip, server, fault);
+        if let Some(termination) = inject(&mut socket, fault).await {
+            eprintln!("Connection from {} to {} closed: {} after {:?}", ip, server, termination, start.elapsed());
+            balancer.metrics.connection_closed(termination);
+            return;
+        }
+    }
+
+    // Établit une connexion avec le serveur cible puis relaie les données dans les deux sens
+    let termination = match relay::connect(server, &balancer.timeouts).await {
+        Ok(mut server_socket) => {
+            balancer.report(guard.backend(), true);
+            let mut shaping = Shaping {
+                mirror: balancer.mirroring.as_ref().and_then(|mirroring| mirroring.start(&balancer.timeouts)),
+                ..Shaping::default()
+            };
+            balancer.bandwidth.apply(addr.ip(), &mut shaping);
+            guard.backend().throttles.apply(&mut shaping);
+            if let Some(fault) = &fault {
+                shaping.drop_after = fault.drop_after;
+                if let Some(rate) = fault.throttle {
+                    Throttles::new(Bandwidth::both(rate)).apply(&mut shaping);
+                }
+            }
+            relay::relay(&mut socket, &mut server_socket, &balancer.timeouts, shaping).await
+        }
+        Err(termination) => {
+            balancer.report(guard.backend(), false);
+            termination
+        }
+    };
+
+    if termination == Termination::Completed {
+        println!("Connection from {} to {} closed: {} after {:?}", ip, server, termination, start.elapsed());
+    } else {
+        eprintln!("Connection from {} to {} closed: {} after {:?}", ip, server, termination, start.elapsed());
+    }
+    balancer.metrics.connection_closed(termination);
+}
+
+// Applique les fautes qui précèdent la connexion au serveur cible. Retourne la raison de la
+// fermeture si la faute termine la connexion du client.
+async fn inject(socket: &mut Stream, fault: &Fault) -> Option<Termination> {
+    if let Some(delay) = fault.delay {
+        tokio::time::sleep(delay).await;
+    }
+    if let Some(status) = fault.abort {
+        let response = format!("HTTP/1.1 {} Fault Injected\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status);
+        let _ = socket.write_all(response.as_bytes()).await;
+        let _ = socket.shutdown().await;
+        return Some(Termination::FaultAbort);
+    }
+    if fault.reset {
+        // Sans délai de linger, la fermeture envoie un RST au client TCP
+        let _ = socket.set_zero_linger();
+        return Some(Termination::FaultReset);
+    }
+    None
+}
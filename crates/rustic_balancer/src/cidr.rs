@@ -0,0 +1,60 @@
+//! Blocs d'adresses IP en notation CIDR.
+
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
+/// Bloc d'adresses IPv4 ou IPv6, par exemple `10.0.0.0/8` ou `2001:db8::/32`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    /// Indique si `ip` appartient au bloc. Les adresses IPv6 de la forme `::ffff:a.b.c.d`
+    /// sont comparées comme les adresses IPv4 correspondantes.
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        ip.is_ipv4() == self.network.is_ipv4() && mask(ip, self.prefix, self.prefix) == self.network
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = String;
+
+    /// Lit un bloc `adresse/préfixe`, ou une adresse seule qui forme un bloc d'une adresse.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (ip, prefix) = s.split_once('/').map_or((s, None), |(ip, prefix)| (ip, Some(prefix)));
+        let ip = ip.parse::<IpAddr>().map_err(|_| format!("invalid address: {}", s))?.to_canonical();
+        let max = if ip.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix.parse::<u8>().ok().filter(|p| *p <= max).ok_or_else(|| format!("invalid prefix: {}", s))?,
+            None => max,
+        };
+        Ok(Self { network: mask(ip, prefix, prefix), prefix })
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix)
+    }
+}
+
+/// Ramène une adresse IP au réseau de préfixe donné.
+pub fn mask(ip: IpAddr, v4_prefix: u8, v6_prefix: u8) -> IpAddr {
+    match ip {
+        IpAddr::V4(ip) => {
+            let prefix = u32::from(v4_prefix.min(32));
+            let bits = if prefix == 0 { 0 } else { u32::MAX << (32 - prefix) };
+            IpAddr::V4(Ipv4Addr::from(u32::from(ip) & bits))
+        }
+        IpAddr::V6(ip) => {
+            let prefix = u32::from(v6_prefix.min(128));
+            let bits = if prefix == 0 { 0 } else { u128::MAX << (128 - prefix) };
+            IpAddr::V6(Ipv6Addr::from(u128::from(ip) & bits))
+        }
+    }
+}
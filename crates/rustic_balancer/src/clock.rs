@@ -0,0 +1,57 @@
+//! Source du temps pour la logique qui en dépend : affinité des clients, seaux à jetons,
+//! éjection des serveurs défaillants et démarrage lent.
+//!
+//! En production l'heure vient de `SystemClock`. Les tests utilisent `ManualClock`, que l'on
+//! avance à la main pour vérifier ces comportements instantanément et sans aléa. Les délais
+//! d'entrée-sortie des connexions (`Timeouts`, file d'attente, drainage) restent sur le
+//! minuteur de Tokio.
+
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Horloge monotone.
+pub trait Clock: Send + Sync {
+    /// Instant courant.
+    fn now(&self) -> Instant;
+}
+
+/// Horloge réelle, celle de Tokio.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Horloge arrêtée, qui n'avance que par `advance`.
+#[derive(Debug)]
+pub struct ManualClock {
+    now: Mutex<Instant>,
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ManualClock {
+    /// Crée une horloge arrêtée à l'instant courant.
+    pub fn new() -> Self {
+        Self { now: Mutex::new(Instant::now()) }
+    }
+
+    /// Avance l'horloge de `duration`.
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().unwrap()
+    }
+}
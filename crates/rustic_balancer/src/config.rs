@@ -0,0 +1,326 @@
+//! Configuration du load balancer, lue depuis la ligne de commande.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::acl::Rules;
+use crate::backend::Backend;
+use crate::fault::{self, Fault};
+use crate::net;
+use crate::outlier::OutlierDetection;
+use crate::priority::Failover;
+use crate::slowstart::SlowStart;
+use crate::split::Split;
+use crate::throttle::Bandwidth;
+use crate::ratelimit::{ClientLimits, Rate};
+use crate::relay::Timeouts;
+
+// Adresse d'écoute par défaut du load balancer
+const DEFAULT_LISTEN: &str = "127.0.0.1:7878";
+
+// Délai par défaut laissé aux connexions en cours lors de l'arrêt
+const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Délais par défaut appliqués à chaque connexion
+const DEFAULT_TIMEOUTS: Timeouts = Timeouts {
+    connect: Some(Duration::from_secs(5)),
+    first_byte: Some(Duration::from_secs(30)),
+    idle: Some(Duration::from_secs(300)),
+    lifetime: None,
+};
+
+// Intervalle par défaut de vérification du fichier de règles d'accès
+const DEFAULT_ACL_RELOAD: Duration = Duration::from_secs(5);
+
+// Délai d'attente par défaut dans la file quand tous les serveurs sont pleins
+const DEFAULT_QUEUE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Limites de connexions simultanées.
+#[derive(Clone, Copy, Debug)]
+pub struct Limits {
+    /// Nombre maximal de connexions simultanées sur le listener.
+    pub max_connections: Option<usize>,
+    /// Nombre de connexions pouvant attendre qu'un serveur se libère.
+    pub queue_size: usize,
+    /// Durée maximale d'attente dans la file.
+    pub queue_timeout: Duration,
+}
+
+/// Configuration du load balancer, lue depuis la ligne de commande.
+///
+/// Options reconnues :
+///
+/// * `--listen <adresse>` - adresse d'écoute `ip:port` ou `unix:<chemin>` (par défaut `127.0.0.1:7878`).
+/// * `--unix-mode <octal>` - droits du fichier du socket Unix d'écoute, par exemple `660` (par défaut
+///   ceux donnés par l'umask).
+/// * `--server <adresse>[,max_conns=<n>][,weight=<n>][,priority=<n>][,pool=<nom>][,bandwidth=<débit>][,upload=<débit>][,download=<débit>]` -
+///   serveur cible, répétable (par défaut les serveurs de `SERVERS`), voir `Backend::parse`.
+/// * `--drain-timeout <secondes>` - délai laissé aux connexions en cours à l'arrêt (par défaut 30).
+/// * `--workers <n>` - nombre de sockets d'écoute liés avec `SO_REUSEPORT` (par défaut 1).
+/// * `--worker-runtimes` - donne à chaque worker son propre thread et runtime mono-thread.
+/// * `--pin-cpus` - épingle chaque worker sur un CPU (implique `--worker-runtimes`).
+/// * `--connect-timeout <secondes>` - délai de connexion au serveur cible (par défaut 5).
+/// * `--first-byte-timeout <secondes>` - délai avant le premier octet du client (par défaut 30).
+/// * `--idle-timeout <secondes>` - délai d'inactivité du relais (par défaut 300).
+/// * `--max-lifetime <secondes>` - durée de vie maximale d'une connexion (illimitée par défaut).
+/// * `--admin <adresse>` - adresse TCP de l'interface d'administration (désactivée par défaut).
+/// * `--max-connections <n>` - nombre maximal de connexions simultanées sur le listener (illimité par défaut).
+/// * `--queue-size <n>` - connexions pouvant attendre quand tous les serveurs sont pleins (0 par défaut).
+/// * `--queue-timeout <secondes>` - durée maximale d'attente dans la file (par défaut 5).
+/// * `--ip-rate <par seconde>[,burst=<n>]` - débit de nouvelles connexions par adresse IP (illimité par défaut).
+/// * `--cidr-rate <par seconde>[,burst=<n>][,v4=<préfixe>][,v6=<préfixe>]` - débit de nouvelles connexions
+///   par réseau, `/24` en IPv4 et `/64` en IPv6 par défaut (illimité par défaut).
+/// * `--affinity-v6-prefix <préfixe>` - regroupe les clients IPv6 par réseau de ce préfixe pour
+///   l'affinité et la répartition entre pools, par exemple `64` (par défaut `128`, chaque adresse à part).
+/// * `--max-conns-per-ip <n>` - connexions simultanées par adresse IP (illimitées par défaut).
+/// * `--allow <cidr>` - n'accepte que les adresses de ce bloc, répétable.
+/// * `--deny <cidr>` - refuse les adresses de ce bloc, répétable.
+/// * `--acl-file <chemin>` - fichier de règles `allow`/`deny`, relu dès qu'il est modifié.
+/// * `--acl-reload <secondes>` - intervalle de vérification du fichier de règles, strictement
+///   positif (par défaut 5).
+/// * `--outlier-detection <options>|default` - éjecte les serveurs défaillants (désactivé par défaut),
+///   voir `OutlierDetection::parse`.
+/// * `--slow-start <secondes>[,aggression=<a>][,min_weight=<fraction>]` - montée en charge progressive
+///   des serveurs au démarrage et à leur réintégration (désactivée par défaut), voir `SlowStart::parse`.
+/// * `--failover-threshold <pourcentage>` - part du poids d'un niveau de priorité qui doit rester
+///   disponible pour que les niveaux suivants ne reçoivent pas de trafic (par défaut 70).
+/// * `--failback <sticky|immediate>` - au retour des serveurs principaux, garde les affinités vers les
+///   serveurs de secours jusqu'à leur expiration (`sticky`, par défaut) ou les abandonne (`immediate`).
+/// * `--split <pool>=<pourcentage>[,...]` - répartit les clients entre pools de serveurs (désactivé par
+///   défaut), voir `Split::parse`.
+/// * `--mirror <adresse>` - serveur fantôme recevant une copie du trafic des clients, répétable
+///   (désactivé par défaut).
+/// * `--mirror-percent <pourcentage>` - part des connexions copiées vers les serveurs fantômes (par défaut 100).
+/// * `--bandwidth <débits>` - débit total du listener, toutes connexions confondues, voir
+///   `Bandwidth::parse` (illimité par défaut).
+/// * `--client-bandwidth <débits>` - débit de chaque adresse IP cliente, toutes ses connexions confondues.
+/// * `--connection-bandwidth <débits>` - débit de chaque connexion.
+/// * `--fault <pool>:<fautes>` - injecte des fautes dans les connexions vers un pool, répétable
+///   (désactivé par défaut), voir `Fault::parse`.
+///
+/// Pour les quatre délais de connexion, la valeur `0` désactive le délai.
+pub struct Config {
+    pub listen: String,
+    pub unix_mode: Option<u32>,
+    pub servers: Vec<Backend>,
+    pub drain_timeout: Duration,
+    pub workers: usize,
+    pub worker_runtimes: bool,
+    pub pin_cpus: bool,
+    pub timeouts: Timeouts,
+    pub admin: Option<String>,
+    pub limits: Limits,
+    pub clients: ClientLimits,
+    pub acl: Rules,
+    pub acl_file: Option<PathBuf>,
+    pub acl_reload: Duration,
+    pub outliers: Option<OutlierDetection>,
+    pub slow_start: Option<SlowStart>,
+    pub failover: Failover,
+    pub affinity_v6_prefix: u8,
+    pub split: Option<Split>,
+    pub mirror_servers: Vec<String>,
+    pub mirror_percent: u32,
+    pub faults: Vec<(String, Fault)>,
+    pub bandwidth: Bandwidth,
+    pub client_bandwidth: Bandwidth,
+    pub connection_bandwidth: Bandwidth,
+}
+
+impl Config {
+    /// Lit la configuration depuis les arguments du processus.
+    pub fn from_args() -> Result<Self, String> {
+        Self::parse(std::env::args().skip(1))
+    }
+
+    /// Lit la configuration depuis une liste d'arguments (sans le nom du programme).
+    ///
+    /// # Errors
+    ///
+    /// Retourne un message d'erreur si une option est inconnue, n'a pas de valeur ou a une valeur invalide.
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self, String> {
+        let mut listen = DEFAULT_LISTEN.to_string();
+        let mut unix_mode = None;
+        let mut servers = Vec::new();
+        let mut drain_timeout = DEFAULT_DRAIN_TIMEOUT;
+        let mut workers = 1;
+        let mut worker_runtimes = false;
+        let mut pin_cpus = false;
+        let mut timeouts = DEFAULT_TIMEOUTS;
+        let mut admin = None;
+        let mut limits = Limits {
+            max_connections: None,
+            queue_size: 0,
+            queue_timeout: DEFAULT_QUEUE_TIMEOUT,
+        };
+        let mut clients = ClientLimits {
+            cidr_v4_prefix: 24,
+            cidr_v6_prefix: 64,
+            ..ClientLimits::default()
+        };
+        let mut acl = Rules::default();
+        let mut acl_file = None;
+        let mut acl_reload = DEFAULT_ACL_RELOAD;
+        let mut outliers = None;
+        let mut slow_start = None;
+        let mut failover = Failover::default();
+        let mut affinity_v6_prefix = 128;
+        let mut split = None;
+        let mut mirror_servers = Vec::new();
+        let mut mirror_percent = 100;
+        let mut faults = Vec::new();
+        let mut bandwidth = Bandwidth::default();
+        let mut client_bandwidth = Bandwidth::default();
+        let mut connection_bandwidth = Bandwidth::default();
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("missing value for {}", arg));
+            match arg.as_str() {
+                "--listen" => listen = parse_addr(value()?)?,
+                "--unix-mode" => unix_mode = Some(parse_mode(&value()?)?),
+                "--server" => servers.push(Backend::parse(&value()?)?),
+                "--drain-timeout" => drain_timeout = parse_secs(&value()?)?,
+                "--workers" => workers = parse_count(&value()?)?,
+                "--worker-runtimes" => worker_runtimes = true,
+                "--pin-cpus" => pin_cpus = true,
+                "--connect-timeout" => timeouts.connect = parse_timeout(&value()?)?,
+                "--first-byte-timeout" => timeouts.first_byte = parse_timeout(&value()?)?,
+                "--idle-timeout" => timeouts.idle = parse_timeout(&value()?)?,
+                "--max-lifetime" => timeouts.lifetime = parse_timeout(&value()?)?,
+                "--admin" => {
+                    let addr = parse_addr(value()?)?;
+                    if net::unix_path(&addr).is_some() {
+                        return Err(format!("{} does not support unix sockets: {}", arg, addr));
+                    }
+                    admin = Some(addr);
+                }
+                "--max-connections" => limits.max_connections = Some(parse_count(&value()?)?),
+                "--queue-size" => limits.queue_size = value()?.parse().map_err(|_| format!("invalid count for {}", arg))?,
+                "--queue-timeout" => limits.queue_timeout = parse_secs(&value()?)?,
+                "--ip-rate" => {
+                    let (rate, options) = Rate::parse(&value()?)?;
+                    if let Some((key, _)) = options.first() {
+                        return Err(format!("unknown rate option: {}", key));
+                    }
+                    clients.ip_rate = Some(rate);
+                }
+                "--cidr-rate" => clients.set_cidr_rate(&value()?)?,
+                "--affinity-v6-prefix" => {
+                    let prefix = value()?;
+                    affinity_v6_prefix = prefix.parse().ok().filter(|p| *p <= 128).ok_or_else(|| format!("invalid prefix: {}", prefix))?;
+                }
+                "--max-conns-per-ip" => clients.max_conns_per_ip = Some(parse_count(&value()?)?),
+                "--allow" => acl.allow.push(value()?.parse()?),
+                "--deny" => acl.deny.push(value()?.parse()?),
+                "--acl-file" => acl_file = Some(PathBuf::from(value()?)),
+                "--acl-reload" => {
+                    let interval = parse_secs(&value()?)?;
+                    acl_reload = Some(interval).filter(|i| !i.is_zero()).ok_or_else(|| format!("invalid interval for {}", arg))?;
+                }
+                "--outlier-detection" => outliers = Some(OutlierDetection::parse(&value()?)?),
+                "--slow-start" => slow_start = Some(SlowStart::parse(&value()?)?),
+                "--failover-threshold" => {
+                    let percent = value()?.parse().ok().filter(|p| (1..=100).contains(p));
+                    failover.threshold_percent = percent.ok_or_else(|| format!("invalid percentage for {}", arg))?;
+                }
+                "--failback" => {
+                    failover.sticky_failback = match value()?.as_str() {
+                        "sticky" => true,
+                        "immediate" => false,
+                        other => return Err(format!("invalid failback mode: {}", other)),
+                    }
+                }
+                "--split" => split = Some(Split::parse(&value()?)?),
+                "--mirror" => mirror_servers.push(parse_addr(value()?)?),
+                "--mirror-percent" => {
+                    let percent = value()?.parse().ok().filter(|p| *p <= 100);
+                    mirror_percent = percent.ok_or_else(|| format!("invalid percentage for {}", arg))?;
+                }
+                "--fault" => faults.push(fault::parse_route(&value()?)?),
+                "--bandwidth" => bandwidth = Bandwidth::parse(&value()?)?,
+                "--client-bandwidth" => client_bandwidth = Bandwidth::parse(&value()?)?,
+                "--connection-bandwidth" => connection_bandwidth = Bandwidth::parse(&value()?)?,
+                _ => return Err(format!("unknown option: {}", arg)),
+            }
+        }
+
+        // Sans serveur explicite, on garde les serveurs historiques
+        if servers.is_empty() {
+            servers = crate::SERVERS.iter().map(|s| Backend::new(s)).collect();
+        }
+        let pools: Vec<&str> = servers.iter().map(|s| s.pool.as_str()).collect();
+        if let Some(split) = &split {
+            split.check_pools(&pools)?;
+        }
+        if let Some((pool, _)) = faults.iter().find(|(pool, _)| !pools.contains(&pool.as_str())) {
+            return Err(format!("no server in pool: {}", pool));
+        }
+
+        Ok(Self {
+            listen,
+            unix_mode,
+            servers,
+            drain_timeout,
+            workers,
+            worker_runtimes: worker_runtimes || pin_cpus,
+            pin_cpus,
+            timeouts,
+            admin,
+            limits,
+            clients,
+            acl,
+            acl_file,
+            acl_reload,
+            outliers,
+            slow_start,
+            failover,
+            affinity_v6_prefix,
+            split,
+            mirror_servers,
+            mirror_percent,
+            faults,
+            bandwidth,
+            client_bandwidth,
+            connection_bandwidth,
+        })
+    }
+}
+
+// Convertit un nombre de secondes (éventuellement décimal) en durée
+fn parse_secs(value: &str) -> Result<Duration, String> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|secs| secs.is_finite() && *secs >= 0.0)
+        .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+        .ok_or_else(|| format!("invalid duration: {}", value))
+}
+
+// Convertit un délai en secondes, `0` désactivant le délai
+fn parse_timeout(value: &str) -> Result<Option<Duration>, String> {
+    parse_secs(value).map(|timeout| Some(timeout).filter(|t| !t.is_zero()))
+}
+
+// Vérifie une adresse `ip:port`, `[ipv6]:port`, `nom:port` ou `unix:<chemin>`
+fn parse_addr(addr: String) -> Result<String, String> {
+    net::check_addr(&addr)?;
+    Ok(addr)
+}
+
+// Convertit des droits de fichier écrits en octal, avec ou sans préfixe `0o`
+fn parse_mode(value: &str) -> Result<u32, String> {
+    u32::from_str_radix(value.trim_start_matches("0o"), 8)
+        .ok()
+        .filter(|mode| *mode <= 0o7777)
+        .ok_or_else(|| format!("invalid mode: {}", value))
+}
+
+// Convertit un nombre strictement positif
+fn parse_count(value: &str) -> Result<usize, String> {
+    value
+        .parse::<usize>()
+        .ok()
+        .filter(|n| *n > 0)
+        .ok_or_else(|| format!("invalid count: {}", value))
+}
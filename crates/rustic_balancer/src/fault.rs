@@ -0,0 +1,151 @@
+//! Injection de fautes dans le chemin des connexions, pour éprouver la résilience des clients.
+//!
+//! Les fautes sont réglées par pool de serveurs (`pool=<nom>`, `default` sans pool) : une part
+//! des connexions envoyées vers ce pool est retardée, refusée avec une réponse d'erreur HTTP,
+//! réinitialisée, coupée après quelques octets ou ralentie. Elles se modifient à chaud depuis
+//! l'interface d'administration, et chaque faute injectée est écrite dans les logs.
+
+use rand::{thread_rng, Rng};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::RwLock;
+use std::time::Duration;
+
+use crate::throttle;
+
+/// Fautes appliquées aux connexions d'un pool.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fault {
+    /// Attente avant la connexion au serveur cible.
+    pub delay: Option<Duration>,
+    /// Répond ce statut HTTP au client sans contacter le serveur cible.
+    pub abort: Option<u16>,
+    /// Réinitialise la connexion du client sans contacter le serveur cible.
+    pub reset: bool,
+    /// Ferme la connexion après ce nombre d'octets relayés, dans les deux sens confondus.
+    pub drop_after: Option<u64>,
+    /// Débit maximal de chaque sens de la connexion, en octets par seconde.
+    pub throttle: Option<u64>,
+    /// Part des connexions touchées, en pourcentage.
+    pub percent: u32,
+}
+
+impl Fault {
+    /// Lit des fautes de la forme `delay=<secondes>,abort=<statut>,reset,drop_after=<octets>,
+    /// throttle=<octets/s>,percent=<pourcentage>`, toutes facultatives. Les options peuvent aussi
+    /// être séparées par `&`, comme dans une requête d'administration.
+    ///
+    /// Par défaut toutes les connexions sont touchées.
+    ///
+    /// # Errors
+    ///
+    /// Retourne un message d'erreur si une option est inconnue ou invalide, si aucune faute
+    /// n'est donnée ou si `abort` et `reset` sont demandés ensemble.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut fault = Self { delay: None, abort: None, reset: false, drop_after: None, throttle: None, percent: 100 };
+        for option in spec.split([',', '&']).map(str::trim).filter(|o| !o.is_empty()) {
+            let (key, value) = match option.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => (option, ""),
+            };
+            let invalid = || format!("invalid value for {}: {}", key, value);
+            match key {
+                "delay" => {
+                    let delay = value.parse::<f64>().ok().and_then(|v| Duration::try_from_secs_f64(v).ok());
+                    fault.delay = Some(delay.ok_or_else(invalid)?);
+                }
+                "abort" => fault.abort = Some(value.parse().ok().filter(|s| (100..=599).contains(s)).ok_or_else(invalid)?),
+                "reset" if value.is_empty() => fault.reset = true,
+                "drop_after" => fault.drop_after = Some(value.parse().map_err(|_| invalid())?),
+                "throttle" => fault.throttle = Some(throttle::parse_rate(value)?),
+                "percent" => fault.percent = value.parse().ok().filter(|p| *p <= 100).ok_or_else(invalid)?,
+                _ => return Err(format!("unknown fault option: {}", option)),
+            }
+        }
+        if fault.abort.is_some() && fault.reset {
+            return Err("abort and reset cannot be combined".to_string());
+        }
+        if fault.delay.is_none() && fault.abort.is_none() && !fault.reset && fault.drop_after.is_none() && fault.throttle.is_none() {
+            return Err(format!("no fault given: {}", spec));
+        }
+        Ok(fault)
+    }
+
+    /// Tire au sort si une nouvelle connexion est touchée.
+    pub fn applies(&self) -> bool {
+        thread_rng().gen_range(0..100) < self.percent
+    }
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(delay) = self.delay {
+            write!(f, "delay={},", delay.as_secs_f64())?;
+        }
+        if let Some(status) = self.abort {
+            write!(f, "abort={},", status)?;
+        }
+        if self.reset {
+            write!(f, "reset,")?;
+        }
+        if let Some(bytes) = self.drop_after {
+            write!(f, "drop_after={},", bytes)?;
+        }
+        if let Some(rate) = self.throttle {
+            write!(f, "throttle={},", rate)?;
+        }
+        write!(f, "percent={}", self.percent)
+    }
+}
+
+/// Fautes de chaque pool, modifiables pendant que les connexions sont servies.
+#[derive(Debug, Default)]
+pub struct Faults {
+    routes: RwLock<BTreeMap<String, Fault>>,
+}
+
+impl Faults {
+    /// Crée la table des fautes à partir des fautes configurées au démarrage.
+    pub fn new(routes: impl IntoIterator<Item = (String, Fault)>) -> Self {
+        Self { routes: RwLock::new(routes.into_iter().collect()) }
+    }
+
+    /// Fautes du pool donné, s'il y en a.
+    pub fn get(&self, pool: &str) -> Option<Fault> {
+        self.routes.read().unwrap().get(pool).cloned()
+    }
+
+    /// Remplace les fautes du pool donné.
+    pub fn set(&self, pool: &str, fault: Fault) {
+        self.routes.write().unwrap().insert(pool.to_string(), fault);
+    }
+
+    /// Retire les fautes du pool donné. Retourne `false` s'il n'en avait pas.
+    pub fn remove(&self, pool: &str) -> bool {
+        self.routes.write().unwrap().remove(pool).is_some()
+    }
+}
+
+impl fmt::Display for Faults {
+    /// Une ligne `<pool> <fautes>` par pool.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (pool, fault) in self.routes.read().unwrap().iter() {
+            writeln!(f, "{} {}", pool, fault)?;
+        }
+        Ok(())
+    }
+}
+
+/// Lit une option `--fault <pool>:<fautes>`.
+///
+/// # Errors
+///
+/// Retourne un message d'erreur si le pool manque ou si les fautes sont invalides.
+pub fn parse_route(spec: &str) -> Result<(String, Fault), String> {
+    let (pool, fault) = spec.split_once(':').ok_or_else(|| format!("missing pool in fault: {}", spec))?;
+    let pool = pool.trim();
+    if pool.is_empty() {
+        return Err(format!("missing pool in fault: {}", spec));
+    }
+    Ok((pool.to_string(), Fault::parse(fault)?))
+}
@@ -0,0 +1,56 @@
+//! Load balancer TCP et sockets Unix.
+//!
+//! La bibliothèque regroupe toute la logique du binaire `load_balancer` pour pouvoir
+//! l'embarquer dans un autre service : [`Balancer`] sert les connexions acceptées sur un
+//! [`Listener`] et les relaie vers les serveurs cibles ([`Backend`]) choisis par un [`Pool`].
+//!
+//! ```no_run
+//! use rustic_balancer::{Balancer, Config, Listener};
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let config = Config::parse(["--server".to_string(), "127.0.0.1:8080".to_string()])?;
+//! let listener = Listener::bind(&config.listen, config.workers).await?;
+//! let balancer = Balancer::new(config)?;
+//! balancer.run(listener, async { let _ = tokio::signal::ctrl_c().await; }).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod acl;
+mod admin;
+pub mod backend;
+pub mod balancer;
+pub mod cidr;
+pub mod clock;
+pub mod config;
+pub mod fault;
+pub mod metrics;
+pub mod mirror;
+pub mod net;
+pub mod outlier;
+pub mod pool;
+pub mod priority;
+pub mod ratelimit;
+pub mod relay;
+pub mod shutdown;
+pub mod slowstart;
+pub mod split;
+pub mod throttle;
+pub mod upgrade;
+pub mod worker;
+
+pub use backend::Backend;
+pub use balancer::{Balancer, Listener};
+pub use clock::{Clock, ManualClock, SystemClock};
+pub use config::Config;
+pub use fault::Fault;
+pub use net::Address;
+pub use outlier::OutlierDetection;
+pub use pool::Pool;
+pub use priority::Failover;
+pub use relay::{relay, Shaping, Termination, Timeouts};
+pub use slowstart::SlowStart;
+pub use split::Split;
+
+/// Adresses des serveurs cibles utilisées quand aucun `--server` n'est donné.
+pub const SERVERS: [&str; 2] = ["127.0.0.1:8080", "127.0.0.1:8081"];
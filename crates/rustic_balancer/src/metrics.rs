@@ -0,0 +1,66 @@
+//! Compteurs partagés par tous les workers, exposés au format texte Prometheus.
+
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use std::sync::Arc;
+use tokio::time::Instant;
+
+use crate::backend::Backend;
+use crate::relay::Termination;
+use crate::slowstart::SlowStart;
+
+/// Compteurs du load balancer.
+#[derive(Default)]
+pub struct Metrics {
+    accepted: AtomicU64,
+    closed: [AtomicU64; Termination::ALL.len()],
+}
+
+impl Metrics {
+    /// Compte une connexion acceptée.
+    pub fn connection_accepted(&self) {
+        self.accepted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Compte une connexion terminée pour la raison donnée.
+    pub fn connection_closed(&self, reason: Termination) {
+        self.closed[reason as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Retourne le nombre de connexions terminées pour la raison donnée.
+    pub fn closed(&self, reason: Termination) -> u64 {
+        self.closed[reason as usize].load(Ordering::Relaxed)
+    }
+
+    /// Formate les compteurs au format texte Prometheus, avec les connexions actives
+    /// et le poids effectif de chaque serveur cible à l'instant `now`, et le nombre de connexions en file d'attente.
+    pub fn render(&self, servers: &[Arc<Backend>], queued: usize, slow_start: Option<&SlowStart>, now: Instant) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# TYPE rb_connections_accepted_total counter");
+        let _ = writeln!(out, "rb_connections_accepted_total {}", self.accepted.load(Ordering::Relaxed));
+        let _ = writeln!(out, "# TYPE rb_connections_closed_total counter");
+        for reason in Termination::ALL {
+            let _ = writeln!(out, "rb_connections_closed_total{{reason=\"{}\"}} {}", reason, self.closed(reason));
+        }
+        let _ = writeln!(out, "# TYPE rb_connections_queued gauge");
+        let _ = writeln!(out, "rb_connections_queued {}", queued);
+        let _ = writeln!(out, "# TYPE rb_backend_active_connections gauge");
+        for server in servers {
+            let _ = writeln!(out, "rb_backend_active_connections{{backend=\"{}\"}} {}", server.addr, server.active());
+        }
+        let _ = writeln!(out, "# TYPE rb_backend_weight gauge");
+        for server in servers {
+            let _ = writeln!(out, "rb_backend_weight{{backend=\"{}\"}} {}", server.addr, server.effective_weight(now, slow_start));
+        }
+        let _ = writeln!(out, "# TYPE rb_backend_ejected gauge");
+        for server in servers {
+            let _ = writeln!(out, "rb_backend_ejected{{backend=\"{}\"}} {}", server.addr, u8::from(server.health().is_ejected()));
+        }
+        let _ = writeln!(out, "# TYPE rb_backend_ejections_total counter");
+        for server in servers {
+            let _ = writeln!(out, "rb_backend_ejections_total{{backend=\"{}\"}} {}", server.addr, server.health().ejections_total());
+        }
+        out
+    }
+}
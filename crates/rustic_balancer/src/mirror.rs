@@ -0,0 +1,150 @@
+//! Copie du trafic des clients vers un pool de serveurs fantômes.
+//!
+//! Pour une part des connexions, les octets envoyés par le client sont aussi envoyés à un
+//! serveur fantôme, dont les réponses sont lues puis jetées. Le chemin principal ne bloque
+//! jamais sur le fantôme : les copies passent par une file bornée et, si le fantôme ne suit
+//! pas, la copie de la connexion est abandonnée plutôt que de ralentir le client. Les
+//! résultats des connexions fantômes sont comptés à part des connexions principales.
+
+use rand::{thread_rng, Rng};
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::mpsc;
+
+use crate::relay::{self, Termination, Timeouts};
+
+// Nombre de lectures du client en attente d'envoi au fantôme avant d'abandonner la copie
+const QUEUE_SIZE: usize = 64;
+
+// Délai laissé au fantôme pour finir de répondre après la fin de la copie, si aucun délai
+// d'inactivité n'est configuré
+const DEFAULT_SHADOW_IDLE: Duration = Duration::from_secs(30);
+
+/// Pool de serveurs fantômes et compteurs de la copie du trafic.
+pub struct Mirroring {
+    servers: Vec<String>,
+    percent: u32,
+    started: AtomicU64,
+    overflows: AtomicU64,
+    closed: [AtomicU64; Termination::ALL.len()],
+}
+
+impl Mirroring {
+    /// Copie `percent` pour cent des connexions vers les serveurs donnés.
+    pub fn new(servers: Vec<String>, percent: u32) -> Self {
+        Self {
+            servers,
+            percent,
+            started: AtomicU64::new(0),
+            overflows: AtomicU64::new(0),
+            closed: Default::default(),
+        }
+    }
+
+    /// Tire au sort si une nouvelle connexion est copiée et, si oui, ouvre sa connexion fantôme.
+    ///
+    /// La connexion fantôme est établie en arrière-plan : le retour est immédiat.
+    pub fn start(self: &Arc<Self>, timeouts: &Timeouts) -> Option<Mirror> {
+        let mut rng = thread_rng();
+        if self.servers.is_empty() || rng.gen_range(0..100) >= self.percent {
+            return None;
+        }
+        let server = self.servers[rng.gen_range(0..self.servers.len())].clone();
+        let (tx, rx) = mpsc::channel(QUEUE_SIZE);
+        self.started.fetch_add(1, Ordering::Relaxed);
+        tokio::spawn(shadow(server, rx, *timeouts, Arc::clone(self)));
+        Some(Mirror { tx: Some(tx), mirroring: Arc::clone(self) })
+    }
+
+    /// Ajoute les compteurs de la copie du trafic au format texte Prometheus.
+    pub fn render(&self, out: &mut String) {
+        let _ = writeln!(out, "# TYPE rb_mirror_connections_total counter");
+        let _ = writeln!(out, "rb_mirror_connections_total {}", self.started.load(Ordering::Relaxed));
+        let _ = writeln!(out, "# TYPE rb_mirror_overflows_total counter");
+        let _ = writeln!(out, "rb_mirror_overflows_total {}", self.overflows.load(Ordering::Relaxed));
+        let _ = writeln!(out, "# TYPE rb_mirror_closed_total counter");
+        for reason in Termination::ALL {
+            let closed = self.closed[reason as usize].load(Ordering::Relaxed);
+            let _ = writeln!(out, "rb_mirror_closed_total{{reason=\"{}\"}} {}", reason, closed);
+        }
+    }
+
+    fn closed(&self, reason: Termination) {
+        self.closed[reason as usize].fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Côté principal d'une copie : les octets du client y sont déposés sans jamais attendre.
+///
+/// La destruction signale la fin du flux au serveur fantôme.
+pub struct Mirror {
+    tx: Option<mpsc::Sender<Vec<u8>>>,
+    mirroring: Arc<Mirroring>,
+}
+
+impl Mirror {
+    /// Copie des octets envoyés par le client.
+    pub fn send(&mut self, data: &[u8]) {
+        let Some(tx) = &self.tx else { return };
+        match tx.try_send(data.to_vec()) {
+            Ok(()) => {}
+            // Le fantôme ne suit pas : le flux copié serait incomplet, on l'abandonne
+            Err(mpsc::error::TrySendError::Full(_)) => {
+                self.mirroring.overflows.fetch_add(1, Ordering::Relaxed);
+                self.tx = None;
+            }
+            // La connexion fantôme s'est déjà terminée et a été comptée
+            Err(mpsc::error::TrySendError::Closed(_)) => self.tx = None,
+        }
+    }
+}
+
+// Relaie les copies vers le serveur fantôme et jette ses réponses
+async fn shadow(server: String, mut rx: mpsc::Receiver<Vec<u8>>, timeouts: Timeouts, mirroring: Arc<Mirroring>) {
+    let mut socket = match relay::connect(&server, &timeouts).await {
+        Ok(socket) => socket,
+        Err(reason) => {
+            eprintln!("Mirror connection to {} failed: {}", server, reason);
+            mirroring.closed(reason);
+            return;
+        }
+    };
+    let (mut read, mut write) = tokio::io::split(&mut socket);
+
+    // Les réponses sont lues en continu pour que le fantôme ne bloque pas sur ses envois
+    let discard = async {
+        let mut buf = vec![0; 16 * 1024];
+        loop {
+            match read.read(&mut buf).await {
+                Ok(0) => return Termination::Completed,
+                Ok(_) => {}
+                Err(_) => return Termination::BackendError,
+            }
+        }
+    };
+    tokio::pin!(discard);
+
+    let forward = async {
+        while let Some(data) = rx.recv().await {
+            write.write_all(&data).await?;
+        }
+        write.shutdown().await
+    };
+    let reason = tokio::select! {
+        result = forward => match result {
+            Ok(()) => {
+                let idle = timeouts.idle.unwrap_or(DEFAULT_SHADOW_IDLE);
+                tokio::time::timeout(idle, &mut discard).await.unwrap_or(Termination::IdleTimeout)
+            }
+            Err(_) => Termination::BackendError,
+        },
+        reason = &mut discard => reason,
+    };
+    if reason != Termination::Completed {
+        eprintln!("Mirror connection to {} closed: {}", server, reason);
+    }
+    mirroring.closed(reason);
+}
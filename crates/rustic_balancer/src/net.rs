@@ -0,0 +1,279 @@
+//! Sockets TCP ou Unix.
+//!
+//! Les adresses d'écoute et celles des serveurs cibles s'écrivent `ip:port` ou `nom:port` pour
+//! TCP, avec les adresses IPv6 entre crochets (`[::1]:8080`), ou `unix:<chemin>` pour un socket
+//! Unix local. Les clients d'un socket Unix n'ont pas d'adresse IP : ils sont considérés comme
+//! venant de `127.0.0.1` pour les listes d'accès, les limites par client et l'affinité.
+//!
+//! Un listener lié sur `[::]` accepte aussi les clients IPv4. Leurs adresses, vues par le
+//! socket sous la forme `::ffff:a.b.c.d`, sont ramenées à l'adresse IPv4 correspondante.
+//!
+//! Au démarrage, un fichier de socket Unix laissé par un processus arrêté est supprimé avant
+//! d'être lié à nouveau. Il n'est pas supprimé à l'arrêt, car un successeur lancé par une mise
+//! à jour à chaud peut encore s'en servir.
+
+use std::fmt;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+
+/// Préfixe des adresses de sockets Unix.
+pub const UNIX_PREFIX: &str = "unix:";
+
+/// Adresse attribuée aux clients d'un socket Unix.
+pub const UNIX_PEER: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
+
+/// Chemin du socket Unix désigné par `addr`, s'il est de la forme `unix:<chemin>`.
+pub fn unix_path(addr: &str) -> Option<&Path> {
+    addr.strip_prefix(UNIX_PREFIX).map(Path::new)
+}
+
+/// Vérifie qu'une adresse est de la forme `ip:port`, `[ipv6]:port`, `nom:port` ou `unix:<chemin>`,
+/// sans la résoudre.
+///
+/// # Errors
+///
+/// Retourne un message d'erreur si l'adresse n'a pas de port valide, si son hôte est vide ou si
+/// c'est une adresse IPv6 sans crochets.
+pub fn check_addr(addr: &str) -> Result<(), String> {
+    if let Some(path) = unix_path(addr) {
+        return if path.as_os_str().is_empty() { Err(format!("missing socket path: {}", addr)) } else { Ok(()) };
+    }
+    if addr.parse::<SocketAddr>().is_ok() {
+        return Ok(());
+    }
+    let invalid = || format!("invalid address: {}", addr);
+    let (host, port) = addr.rsplit_once(':').ok_or_else(invalid)?;
+    if host.contains(':') {
+        // Sans crochets, le port d'une adresse IPv6 ne peut pas être distingué de l'adresse
+        return Err(format!("invalid address (IPv6 addresses are written [addr]:port): {}", addr));
+    }
+    if host.is_empty() || host.starts_with('[') || port.parse::<u16>().is_err() {
+        return Err(invalid());
+    }
+    Ok(())
+}
+
+/// Adresse d'un socket d'écoute.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Address {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Address::Tcp(addr) => write!(f, "{}", addr),
+            Address::Unix(path) => write!(f, "{}{}", UNIX_PREFIX, path.display()),
+        }
+    }
+}
+
+/// Socket d'écoute TCP ou Unix, pas encore confié à un runtime.
+#[derive(Debug)]
+pub enum ListenSocket {
+    Tcp(std::net::TcpListener),
+    #[cfg(unix)]
+    Unix(std::os::unix::net::UnixListener),
+}
+
+impl ListenSocket {
+    /// Adresse d'écoute.
+    pub fn local_addr(&self) -> io::Result<Address> {
+        match self {
+            ListenSocket::Tcp(listener) => listener.local_addr().map(Address::Tcp),
+            #[cfg(unix)]
+            ListenSocket::Unix(listener) => {
+                let addr = listener.local_addr()?;
+                let path = addr.as_pathname().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "unnamed unix socket"))?;
+                Ok(Address::Unix(path.to_path_buf()))
+            }
+        }
+    }
+
+    /// Copie du socket, qui partage la même file de connexions.
+    pub fn try_clone(&self) -> io::Result<Self> {
+        match self {
+            ListenSocket::Tcp(listener) => listener.try_clone().map(ListenSocket::Tcp),
+            #[cfg(unix)]
+            ListenSocket::Unix(listener) => listener.try_clone().map(ListenSocket::Unix),
+        }
+    }
+
+    /// Change les droits du fichier d'un socket Unix. Sans effet sur un socket TCP.
+    pub fn set_permissions(&self, mode: u32) -> io::Result<()> {
+        #[cfg(unix)]
+        if let Address::Unix(path) = self.local_addr()? {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+        }
+        #[cfg(not(unix))]
+        let _ = mode;
+        Ok(())
+    }
+
+    // Confie le socket au runtime courant
+    pub(crate) fn into_acceptor(self) -> io::Result<Acceptor> {
+        match self {
+            ListenSocket::Tcp(listener) => {
+                listener.set_nonblocking(true)?;
+                tokio::net::TcpListener::from_std(listener).map(Acceptor::Tcp)
+            }
+            #[cfg(unix)]
+            ListenSocket::Unix(listener) => {
+                listener.set_nonblocking(true)?;
+                tokio::net::UnixListener::from_std(listener).map(Acceptor::Unix)
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+impl std::os::fd::AsRawFd for ListenSocket {
+    fn as_raw_fd(&self) -> std::os::fd::RawFd {
+        match self {
+            ListenSocket::Tcp(listener) => listener.as_raw_fd(),
+            ListenSocket::Unix(listener) => listener.as_raw_fd(),
+        }
+    }
+}
+
+/// Lie `count` copies d'un socket Unix sur `path`, en supprimant d'abord le fichier d'un socket
+/// que plus aucun processus n'écoute. Le fichier du socket est créé avec les droits donnés par
+/// l'umask du processus.
+///
+/// # Errors
+///
+/// Retourne une erreur si le chemin est occupé par un autre fichier ou par un socket encore
+/// écouté, ou si le socket ne peut pas être lié.
+#[cfg(unix)]
+pub fn bind_unix(path: &Path, count: usize) -> io::Result<Vec<ListenSocket>> {
+    use std::os::unix::fs::FileTypeExt;
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    let listener = match UnixListener::bind(path) {
+        Err(e) if e.kind() == io::ErrorKind::AddrInUse => {
+            // Un socket qui refuse les connexions n'a plus de processus derrière lui
+            let is_socket = std::fs::symlink_metadata(path).is_ok_and(|meta| meta.file_type().is_socket());
+            let refused = UnixStream::connect(path).is_err_and(|e| e.kind() == io::ErrorKind::ConnectionRefused);
+            if !is_socket || !refused {
+                return Err(e);
+            }
+            eprintln!("Removing stale socket {}", path.display());
+            std::fs::remove_file(path)?;
+            UnixListener::bind(path)?
+        }
+        result => result?,
+    };
+
+    // Les workers se partagent la file de connexions du même socket
+    let mut listeners = Vec::with_capacity(count);
+    for _ in 1..count {
+        listeners.push(ListenSocket::Unix(listener.try_clone()?));
+    }
+    listeners.push(ListenSocket::Unix(listener));
+    Ok(listeners)
+}
+
+#[cfg(not(unix))]
+pub fn bind_unix(_path: &Path, _count: usize) -> io::Result<Vec<ListenSocket>> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "unix sockets require Unix"))
+}
+
+// Socket d'écoute servi par un worker
+pub(crate) enum Acceptor {
+    Tcp(tokio::net::TcpListener),
+    #[cfg(unix)]
+    Unix(tokio::net::UnixListener),
+}
+
+impl Acceptor {
+    // Accepte une connexion et retourne l'adresse du client, IPv4 pour un client IPv4 d'un
+    // listener double pile, `UNIX_PEER` pour un socket Unix
+    pub(crate) async fn accept(&self) -> io::Result<(Stream, SocketAddr)> {
+        match self {
+            Acceptor::Tcp(listener) => {
+                let (socket, addr) = listener.accept().await?;
+                Ok((Stream::Tcp(socket), SocketAddr::new(addr.ip().to_canonical(), addr.port())))
+            }
+            #[cfg(unix)]
+            Acceptor::Unix(listener) => {
+                let (socket, _) = listener.accept().await?;
+                Ok((Stream::Unix(socket), UNIX_PEER))
+            }
+        }
+    }
+}
+
+/// Connexion TCP ou Unix, avec un client ou un serveur cible.
+#[derive(Debug)]
+pub enum Stream {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(tokio::net::UnixStream),
+}
+
+impl Stream {
+    /// Se connecte à une adresse `ip:port` ou `unix:<chemin>`.
+    ///
+    /// # Errors
+    ///
+    /// Retourne une erreur si la connexion échoue.
+    pub async fn connect(addr: &str) -> io::Result<Self> {
+        #[cfg(unix)]
+        if let Some(path) = unix_path(addr) {
+            return tokio::net::UnixStream::connect(path).await.map(Stream::Unix);
+        }
+        TcpStream::connect(addr).await.map(Stream::Tcp)
+    }
+
+    /// Fait envoyer un RST à la fermeture d'une connexion TCP. Sans effet sur un socket Unix.
+    pub fn set_zero_linger(&self) -> io::Result<()> {
+        match self {
+            Stream::Tcp(socket) => socket.set_zero_linger(),
+            #[cfg(unix)]
+            Stream::Unix(_) => Ok(()),
+        }
+    }
+}
+
+impl AsyncRead for Stream {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Stream::Tcp(socket) => Pin::new(socket).poll_read(cx, buf),
+            #[cfg(unix)]
+            Stream::Unix(socket) => Pin::new(socket).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for Stream {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Stream::Tcp(socket) => Pin::new(socket).poll_write(cx, buf),
+            #[cfg(unix)]
+            Stream::Unix(socket) => Pin::new(socket).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Stream::Tcp(socket) => Pin::new(socket).poll_flush(cx),
+            #[cfg(unix)]
+            Stream::Unix(socket) => Pin::new(socket).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Stream::Tcp(socket) => Pin::new(socket).poll_shutdown(cx),
+            #[cfg(unix)]
+            Stream::Unix(socket) => Pin::new(socket).poll_shutdown(cx),
+        }
+    }
+}
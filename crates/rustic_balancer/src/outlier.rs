@@ -0,0 +1,260 @@
+//! Détection passive des serveurs défaillants et coupe-circuit.
+//!
+//! Chaque tentative de connexion vers un serveur cible est comptée comme un succès ou un
+//! échec. Un serveur est éjecté du pool après trop d'échecs consécutifs, ou quand son taux
+//! d'échec sur une fenêtre glissante dépasse un seuil. La durée d'éjection double à chaque
+//! nouvelle éjection rapprochée, jusqu'à un maximum. À la fin de l'éjection, le serveur passe
+//! en semi-ouvert : une seule connexion d'essai à la fois lui est confiée, et il n'est
+//! réintégré qu'une fois cet essai réussi. Un pourcentage maximal du pool peut être éjecté
+//! en même temps, pour ne jamais vider le pool sur une panne du réseau.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Réglages de la détection passive.
+#[derive(Clone, Copy, Debug)]
+pub struct OutlierDetection {
+    /// Nombre d'échecs consécutifs déclenchant l'éjection.
+    pub consecutive_failures: Option<u32>,
+    /// Taux d'échec sur la fenêtre déclenchant l'éjection, entre 0 et 1.
+    pub failure_rate: Option<f64>,
+    /// Durée de la fenêtre glissante du taux d'échec.
+    pub window: Duration,
+    /// Nombre minimal de tentatives dans la fenêtre pour évaluer le taux d'échec.
+    pub min_requests: usize,
+    /// Durée de la première éjection.
+    pub base_ejection: Duration,
+    /// Durée maximale d'une éjection.
+    pub max_ejection: Duration,
+    /// Pourcentage maximal du pool éjecté en même temps.
+    pub max_ejected_percent: usize,
+}
+
+impl Default for OutlierDetection {
+    fn default() -> Self {
+        Self {
+            consecutive_failures: Some(5),
+            failure_rate: None,
+            window: Duration::from_secs(10),
+            min_requests: 10,
+            base_ejection: Duration::from_secs(30),
+            max_ejection: Duration::from_secs(300),
+            max_ejected_percent: 50,
+        }
+    }
+}
+
+impl OutlierDetection {
+    /// Lit des réglages de la forme `option=valeur[,option=valeur...]`, ou `default`.
+    ///
+    /// Options reconnues : `consecutive`, `rate`, `window`, `min_requests`, `base_ejection`,
+    /// `max_ejection` (en secondes) et `max_ejected` (en pourcentage). `consecutive=0` désactive
+    /// le critère des échecs consécutifs.
+    ///
+    /// # Errors
+    ///
+    /// Retourne un message d'erreur si une option est inconnue ou a une valeur invalide.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut detection = Self::default();
+        if spec.trim() == "default" {
+            return Ok(detection);
+        }
+        for option in spec.split(',') {
+            let (key, value) = option
+                .split_once('=')
+                .ok_or_else(|| format!("invalid outlier option: {}", option))?;
+            let value = value.trim();
+            let invalid = || format!("invalid value for {}: {}", key, value);
+            let secs = || {
+                value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|v| v.is_finite() && *v > 0.0)
+                    .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+                    .ok_or_else(invalid)
+            };
+            match key.trim() {
+                "consecutive" => {
+                    let n = value.parse::<u32>().map_err(|_| invalid())?;
+                    detection.consecutive_failures = Some(n).filter(|n| *n > 0);
+                }
+                "rate" => {
+                    let rate = value.parse::<f64>().ok().filter(|r| *r > 0.0 && *r <= 1.0).ok_or_else(invalid)?;
+                    detection.failure_rate = Some(rate);
+                }
+                "window" => detection.window = secs()?,
+                "min_requests" => detection.min_requests = value.parse().map_err(|_| invalid())?,
+                "base_ejection" => detection.base_ejection = secs()?,
+                "max_ejection" => detection.max_ejection = secs()?,
+                "max_ejected" => {
+                    detection.max_ejected_percent = value.parse().ok().filter(|p| *p <= 100).ok_or_else(invalid)?;
+                }
+                _ => return Err(format!("unknown outlier option: {}", key)),
+            }
+        }
+        Ok(detection)
+    }
+
+    // Durée de la n-ième éjection rapprochée (n >= 1)
+    fn ejection_time(&self, ejections: u32) -> Duration {
+        let factor = 2u32.saturating_pow(ejections.saturating_sub(1));
+        self.base_ejection.saturating_mul(factor).min(self.max_ejection)
+    }
+}
+
+/// État du coupe-circuit d'un serveur cible.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Circuit {
+    /// Le serveur reçoit du trafic normalement.
+    Closed,
+    /// Le serveur est éjecté jusqu'à la date donnée.
+    Open(Instant),
+    /// Le serveur reçoit une connexion d'essai à la fois avant d'être réintégré.
+    HalfOpen,
+}
+
+/// Issue d'une tentative enregistrée par `Health::record`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    /// Rien à faire.
+    Healthy,
+    /// Le serveur a dépassé un seuil et devrait être éjecté.
+    ShouldEject,
+    /// La connexion d'essai a réussi : le serveur est réintégré.
+    Reinstated,
+}
+
+/// Santé observée d'un serveur cible.
+#[derive(Debug)]
+pub struct Health {
+    circuit: Circuit,
+    trial_in_flight: bool,
+    consecutive_failures: u32,
+    window: VecDeque<(Instant, bool)>,
+    ejections: u32,
+    ejections_total: u64,
+    reinstated_at: Option<Instant>,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self {
+            circuit: Circuit::Closed,
+            trial_in_flight: false,
+            consecutive_failures: 0,
+            window: VecDeque::new(),
+            ejections: 0,
+            ejections_total: 0,
+            reinstated_at: None,
+        }
+    }
+}
+
+impl Health {
+    /// Indique si le serveur peut recevoir une connexion, en passant en semi-ouvert
+    /// à la fin de l'éjection.
+    pub fn is_available(&mut self, now: Instant) -> bool {
+        if let Circuit::Open(until) = self.circuit {
+            if now < until {
+                return false;
+            }
+            self.circuit = Circuit::HalfOpen;
+            self.trial_in_flight = false;
+        }
+        match self.circuit {
+            Circuit::HalfOpen => !self.trial_in_flight,
+            _ => true,
+        }
+    }
+
+    /// Note que le serveur vient d'être choisi pour une connexion. Retourne `true` si cette
+    /// connexion est la connexion d'essai d'un serveur semi-ouvert.
+    pub fn on_selected(&mut self) -> bool {
+        let trial = self.circuit == Circuit::HalfOpen;
+        if trial {
+            self.trial_in_flight = true;
+        }
+        trial
+    }
+
+    /// Indique si le serveur est éjecté et que son éjection n'est pas terminée à `now`.
+    pub fn is_open(&self, now: Instant) -> bool {
+        matches!(self.circuit, Circuit::Open(until) if now < until)
+    }
+
+    /// Indique si le serveur est éjecté ou en période d'essai.
+    pub fn is_ejected(&self) -> bool {
+        self.circuit != Circuit::Closed
+    }
+
+    /// Nombre total d'éjections du serveur.
+    pub fn ejections_total(&self) -> u64 {
+        self.ejections_total
+    }
+
+    /// Date de la dernière réintégration du serveur, s'il a déjà été éjecté.
+    pub fn reinstated_at(&self) -> Option<Instant> {
+        self.reinstated_at
+    }
+
+    /// Enregistre le résultat d'une tentative de connexion.
+    pub fn record(&mut self, success: bool, now: Instant, detection: &OutlierDetection) -> Verdict {
+        // Fenêtre glissante du taux d'échec
+        self.window.push_back((now, success));
+        while self.window.front().is_some_and(|(at, _)| now.saturating_duration_since(*at) > detection.window) {
+            self.window.pop_front();
+        }
+
+        if success {
+            self.consecutive_failures = 0;
+        } else {
+            self.consecutive_failures += 1;
+        }
+
+        match self.circuit {
+            Circuit::HalfOpen if success => {
+                self.circuit = Circuit::Closed;
+                self.trial_in_flight = false;
+                self.reinstated_at = Some(now);
+                self.window.clear();
+                Verdict::Reinstated
+            }
+            Circuit::HalfOpen => Verdict::ShouldEject,
+            Circuit::Open(_) => Verdict::Healthy,
+            Circuit::Closed if success => Verdict::Healthy,
+            Circuit::Closed => {
+                let consecutive = detection.consecutive_failures.is_some_and(|max| self.consecutive_failures >= max);
+                let failures = self.window.iter().filter(|(_, success)| !success).count();
+                let rate = detection.failure_rate.is_some_and(|max| {
+                    self.window.len() >= detection.min_requests.max(1) && failures as f64 / self.window.len() as f64 >= max
+                });
+                if consecutive || rate {
+                    Verdict::ShouldEject
+                } else {
+                    Verdict::Healthy
+                }
+            }
+        }
+    }
+
+    /// Éjecte le serveur et retourne la durée de l'éjection.
+    ///
+    /// La durée double à chaque éjection, sauf si le serveur est resté sain plus longtemps
+    /// que la durée maximale d'éjection depuis sa dernière réintégration.
+    pub fn eject(&mut self, now: Instant, detection: &OutlierDetection) -> Duration {
+        if self.circuit == Circuit::Closed
+            && self.reinstated_at.is_some_and(|at| now.saturating_duration_since(at) > detection.max_ejection)
+        {
+            self.ejections = 0;
+        }
+        self.ejections += 1;
+        self.ejections_total += 1;
+        let duration = detection.ejection_time(self.ejections);
+        self.circuit = Circuit::Open(now + duration);
+        self.trial_in_flight = false;
+        self.consecutive_failures = 0;
+        self.window.clear();
+        duration
+    }
+}
@@ -0,0 +1,189 @@
+//! Choix du serveur cible de chaque client.
+//!
+//! Un client garde le même serveur pendant 2 secondes tant que celui-ci reste disponible.
+//! Les clients IPv6 peuvent être regroupés par réseau (`/64` par exemple), pour qu'un même
+//! poste qui change d'adresse temporaire garde son serveur et son pool.
+//! Sinon le serveur est tiré au hasard selon son poids, parmi les serveurs disponibles des
+//! niveaux de priorité qui reçoivent du trafic et, avec une répartition, du pool du client.
+
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::{thread_rng, Rng};
+use std::collections::HashMap;
+use std::iter;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+
+use crate::backend::Backend;
+use crate::cidr;
+use crate::clock::{Clock, SystemClock};
+use crate::priority::Failover;
+use crate::slowstart::SlowStart;
+use crate::split::Split;
+use crate::SERVERS;
+
+// Durée pendant laquelle un client garde le même serveur
+const AFFINITY_TTL: Duration = Duration::from_secs(2);
+
+// Nombre de choix de serveur entre deux nettoyages des affinités expirées
+const SWEEP_INTERVAL: u32 = 1024;
+
+/// Clé d'affinité du client `ip` : son adresse IPv4, ou le réseau de préfixe `v6_prefix` qui
+/// contient son adresse IPv6 (`128` garde l'adresse entière). Une adresse `::ffff:a.b.c.d` est
+/// ramenée à l'adresse IPv4 correspondante.
+pub fn affinity_key(ip: IpAddr, v6_prefix: u8) -> String {
+    cidr::mask(ip.to_canonical(), 32, v6_prefix).to_string()
+}
+
+/// Serveurs cibles et choix du serveur de chaque client, avec affinité par adresse IP.
+pub struct Pool {
+    map: HashMap<String, (Arc<Backend>, Instant)>, // Mappe les adresses IP aux serveurs et aux timestamps
+    lookups: u32, // Choix de serveur depuis le dernier nettoyage de `map`
+    servers: Vec<Arc<Backend>>, // Serveurs parmi lesquels choisir
+    slow_start: Option<SlowStart>, // Montée en charge des serveurs réintégrés, si elle est activée
+    failover: Failover, // Bascule vers les serveurs de secours
+    split: Option<Split>, // Répartition des clients entre pools, modifiable depuis l'interface d'administration
+    clock: Arc<dyn Clock>, // Source du temps pour l'affinité, le démarrage lent et les éjections
+}
+
+impl Default for Pool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Pool {
+    /// Crée un pool sur les serveurs par défaut de `SERVERS`.
+    pub fn new() -> Self {
+        Self::with_servers(SERVERS.iter().map(|s| Arc::new(Backend::new(s))).collect())
+    }
+
+    /// Crée un pool sur une liste de serveurs donnée, sans démarrage lent ni répartition, sur l'horloge réelle.
+    pub fn with_servers(servers: Vec<Arc<Backend>>) -> Self {
+        Self {
+            map: HashMap::new(),
+            lookups: 0,
+            servers,
+            slow_start: None,
+            failover: Failover::default(),
+            split: None,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Active la montée en charge progressive des serveurs réintégrés.
+    pub fn with_slow_start(mut self, slow_start: Option<SlowStart>) -> Self {
+        self.slow_start = slow_start;
+        self
+    }
+
+    /// Règle la bascule entre niveaux de priorité.
+    pub fn with_failover(mut self, failover: Failover) -> Self {
+        self.failover = failover;
+        self
+    }
+
+    /// Répartit les clients entre pools de serveurs.
+    pub fn with_split(mut self, split: Option<Split>) -> Self {
+        self.split = split;
+        self
+    }
+
+    /// Remplace l'horloge du pool, par exemple par une `ManualClock` dans les tests.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Serveurs du pool.
+    pub fn servers(&self) -> &[Arc<Backend>] {
+        &self.servers
+    }
+
+    /// Nombre de clients dont l'affinité est encore en mémoire, expirée ou non.
+    pub fn affinity_entries(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Réglages du démarrage lent, s'il est activé.
+    pub fn slow_start(&self) -> Option<&SlowStart> {
+        self.slow_start.as_ref()
+    }
+
+    /// Répartition actuelle entre pools, si elle est configurée.
+    pub fn split(&self) -> Option<&Split> {
+        self.split.as_ref()
+    }
+
+    /// Remplace la répartition entre pools d'un seul coup.
+    ///
+    /// # Errors
+    ///
+    /// Retourne un message d'erreur si un pool de la répartition n'a aucun serveur.
+    pub fn set_split(&mut self, split: Split) -> Result<(), String> {
+        let pools: Vec<&str> = self.servers.iter().map(|s| s.pool.as_str()).collect();
+        split.check_pools(&pools)?;
+        self.split = Some(split);
+        Ok(())
+    }
+
+    /// Choisit le serveur du client `ip`, en gardant son serveur précédent pendant 2 secondes.
+    /// `ip` est une clé d'affinité, voir `affinity_key`.
+    ///
+    /// Retourne `None` si tous les serveurs sont pleins ou éjectés.
+    pub async fn get_server(&mut self, ip: &str) -> Option<Arc<Backend>> {
+        // Serveurs disponibles des niveaux de priorité qui doivent recevoir du trafic
+        let now = self.clock.now();
+
+        // Oublie de temps en temps les affinités expirées, pour que le cache ne grossisse pas
+        // avec chaque client passé
+        self.lookups += 1;
+        if self.lookups >= SWEEP_INTERVAL {
+            self.lookups = 0;
+            self.map.retain(|_, (_, timestamp)| now.duration_since(*timestamp) < AFFINITY_TTL);
+        }
+        let available = match &self.split {
+            None => self.failover.candidates(&self.servers, now),
+            // Le pool du client d'abord, puis les autres pools qui reçoivent du trafic s'il n'a plus de serveur disponible
+            Some(split) => {
+                let pool = split.pool_for(ip);
+                let others = split.shares().iter().filter(|(p, percent)| *percent > 0 && p != pool).map(|(p, _)| p.as_str());
+                iter::once(pool)
+                    .chain(others)
+                    .map(|pool| self.failover.candidates(self.servers.iter().filter(|s| s.pool == pool), now))
+                    .find(|candidates| !candidates.is_empty())
+                    .unwrap_or_default()
+            }
+        };
+
+        // Vérifie si l'adresse IP est déjà dans le cache
+        if let Some((server, timestamp)) = self.map.get(ip) {
+            // Vérifie si le cache est encore valide (moins de 2 secondes) et si le serveur est encore disponible.
+            // Le serveur doit appartenir au pool choisi pour le client et, sans affinité au retour des
+            // serveurs principaux, faire aussi partie des candidats
+            let in_pool = self.split.is_none() || available.first().is_some_and(|s| s.pool == server.pool);
+            let kept = in_pool && (self.failover.sticky_failback || available.iter().any(|s| Arc::ptr_eq(s, server)));
+            if now.duration_since(*timestamp) < AFFINITY_TTL && kept && server.is_available(now) {
+                return Some(server.clone()); // Retourne le serveur associé
+            }
+        }
+
+        // Choisis un serveur aléatoire, selon son poids, parmi ceux qui ont encore de la place et ne sont pas éjectés
+        if available.is_empty() {
+            return None;
+        }
+        let mut rng = thread_rng();
+        let weights = available.iter().map(|s| s.effective_weight(now, self.slow_start.as_ref()));
+        let index = match WeightedIndex::new(weights) {
+            Ok(index) => index.sample(&mut rng),
+            // Tous les poids sont nuls : on revient à un choix uniforme
+            Err(_) => rng.gen_range(0..available.len()),
+        };
+        let server = available[index].clone();
+
+        // Ajoute l'adresse IP, le serveur et le timestamp au cache
+        self.map.insert(ip.to_string(), (server.clone(), now));
+        Some(server) // Retourne le serveur choisi
+    }
+}
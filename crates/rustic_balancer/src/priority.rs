@@ -0,0 +1,61 @@
+//! Niveaux de priorité des serveurs cibles : serveurs principaux et serveurs de secours.
+//!
+//! Chaque serveur a une priorité, `0` pour les serveurs principaux et une valeur plus grande
+//! pour les secours. Les niveaux sont parcourus du plus prioritaire au moins prioritaire :
+//! tant que la part disponible du poids d'un niveau atteint le seuil de bascule, le trafic
+//! reste sur les niveaux déjà parcourus. En dessous, le niveau suivant est ajouté aux
+//! candidats. Le trafic revient de lui-même vers les serveurs principaux quand ils se
+//! rétablissent.
+
+use std::sync::Arc;
+use tokio::time::Instant;
+
+use crate::backend::Backend;
+
+/// Pourcentage de poids disponible en dessous duquel un niveau déborde sur le suivant.
+pub const DEFAULT_THRESHOLD: usize = 70;
+
+/// Réglages de la bascule entre niveaux de priorité.
+#[derive(Clone, Copy, Debug)]
+pub struct Failover {
+    /// Pourcentage du poids d'un niveau qui doit être disponible pour ne pas déborder.
+    pub threshold_percent: usize,
+    /// Garde l'affinité vers un serveur de secours quand les serveurs principaux reviennent.
+    pub sticky_failback: bool,
+}
+
+impl Default for Failover {
+    fn default() -> Self {
+        Self { threshold_percent: DEFAULT_THRESHOLD, sticky_failback: true }
+    }
+}
+
+impl Failover {
+    /// Retourne les serveurs disponibles des niveaux qui doivent recevoir du trafic.
+    ///
+    /// La liste est vide si aucun serveur n'est disponible.
+    pub fn candidates<'a>(&self, servers: impl IntoIterator<Item = &'a Arc<Backend>>, now: Instant) -> Vec<&'a Arc<Backend>> {
+        let servers: Vec<&Arc<Backend>> = servers.into_iter().collect();
+        let mut priorities: Vec<u8> = servers.iter().map(|s| s.priority).collect();
+        priorities.sort_unstable();
+        priorities.dedup();
+
+        let mut candidates = Vec::new();
+        for priority in priorities {
+            let mut total = 0;
+            let mut available = 0;
+            for server in servers.iter().copied().filter(|s| s.priority == priority) {
+                let weight = u64::from(server.weight);
+                total += weight;
+                if server.is_available(now) {
+                    available += weight;
+                    candidates.push(server);
+                }
+            }
+            if available * 100 >= total * self.threshold_percent as u64 {
+                break;
+            }
+        }
+        candidates
+    }
+}
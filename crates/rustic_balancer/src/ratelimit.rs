@@ -0,0 +1,244 @@
+//! Limitation des nouvelles connexions par adresse IP source.
+//!
+//! Trois protections indépendantes, toutes désactivées par défaut :
+//!
+//! * un seau à jetons par adresse IP pour le débit de nouvelles connexions ;
+//! * un seau à jetons par préfixe réseau (par exemple `/24` en IPv4 et `/64` en IPv6),
+//!   pour les clients qui changent d'adresse dans un même réseau ;
+//! * un nombre maximal de connexions simultanées par adresse IP.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use tokio::time::Instant;
+
+use crate::cidr::mask;
+use crate::clock::{Clock, SystemClock};
+use crate::relay::Termination;
+
+// Nombre de vérifications entre deux nettoyages des seaux inutilisés
+const SWEEP_INTERVAL: u32 = 1024;
+
+/// Débit autorisé pour un seau à jetons.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rate {
+    /// Jetons ajoutés par seconde.
+    pub per_second: f64,
+    /// Nombre maximal de jetons accumulés.
+    pub burst: f64,
+}
+
+impl Rate {
+    /// Lit un débit de la forme `<par seconde>[,burst=<n>]`. Sans `burst`, la rafale vaut le débit.
+    ///
+    /// Les options inconnues sont retournées à l'appelant sous forme de paires clé/valeur.
+    ///
+    /// # Errors
+    ///
+    /// Retourne un message d'erreur si le débit ou la rafale ne sont pas des nombres positifs.
+    pub fn parse(spec: &str) -> Result<(Self, Vec<(String, String)>), String> {
+        let mut parts = spec.split(',');
+        let per_second = parse_positive(parts.next().unwrap_or_default())?;
+        let mut rate = Rate { per_second, burst: per_second.max(1.0) };
+        let mut others = Vec::new();
+        for option in parts {
+            let (key, value) = option
+                .split_once('=')
+                .ok_or_else(|| format!("invalid rate option: {}", option))?;
+            match key.trim() {
+                "burst" => rate.burst = parse_positive(value)?.max(1.0),
+                key => others.push((key.to_string(), value.trim().to_string())),
+            }
+        }
+        Ok((rate, others))
+    }
+}
+
+fn parse_positive(value: &str) -> Result<f64, String> {
+    value
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|v| v.is_finite() && *v > 0.0)
+        .ok_or_else(|| format!("invalid rate: {}", value))
+}
+
+/// Configuration des limites par client.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ClientLimits {
+    /// Débit de nouvelles connexions par adresse IP.
+    pub ip_rate: Option<Rate>,
+    /// Débit de nouvelles connexions par préfixe réseau.
+    pub cidr_rate: Option<Rate>,
+    /// Longueur du préfixe IPv4 regroupant les clients pour `cidr_rate`.
+    pub cidr_v4_prefix: u8,
+    /// Longueur du préfixe IPv6 regroupant les clients pour `cidr_rate`.
+    pub cidr_v6_prefix: u8,
+    /// Nombre maximal de connexions simultanées par adresse IP.
+    pub max_conns_per_ip: Option<usize>,
+}
+
+impl ClientLimits {
+    /// Lit l'option `--cidr-rate <par seconde>[,burst=<n>][,v4=<préfixe>][,v6=<préfixe>]`.
+    ///
+    /// # Errors
+    ///
+    /// Retourne un message d'erreur si le débit ou un préfixe est invalide.
+    pub fn set_cidr_rate(&mut self, spec: &str) -> Result<(), String> {
+        let (rate, options) = Rate::parse(spec)?;
+        for (key, value) in options {
+            let prefix = value.parse::<u8>().map_err(|_| format!("invalid prefix: {}", value))?;
+            match key.as_str() {
+                "v4" if prefix <= 32 => self.cidr_v4_prefix = prefix,
+                "v6" if prefix <= 128 => self.cidr_v6_prefix = prefix,
+                "v4" | "v6" => return Err(format!("invalid prefix: {}", value)),
+                _ => return Err(format!("unknown rate option: {}", key)),
+            }
+        }
+        self.cidr_rate = Some(rate);
+        Ok(())
+    }
+
+    /// Indique si au moins une limite est active.
+    pub fn is_enabled(&self) -> bool {
+        self.ip_rate.is_some() || self.cidr_rate.is_some() || self.max_conns_per_ip.is_some()
+    }
+}
+
+// Seau à jetons
+struct Bucket {
+    tokens: f64,
+    last: Instant,
+}
+
+impl Bucket {
+    fn new(rate: &Rate, now: Instant) -> Self {
+        Self { tokens: rate.burst, last: now }
+    }
+
+    // Remplit le seau selon le temps écoulé puis consomme un jeton s'il y en a un
+    fn take(&mut self, rate: &Rate, now: Instant) -> bool {
+        self.refill(rate, now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn refill(&mut self, rate: &Rate, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate.per_second).min(rate.burst);
+        self.last = now;
+    }
+
+    // Un seau plein se comporte comme un seau neuf : il peut être oublié
+    fn is_full(&mut self, rate: &Rate, now: Instant) -> bool {
+        self.refill(rate, now);
+        self.tokens >= rate.burst
+    }
+}
+
+#[derive(Default)]
+struct State {
+    ip_buckets: HashMap<IpAddr, Bucket>,
+    cidr_buckets: HashMap<IpAddr, Bucket>,
+    active: HashMap<IpAddr, usize>,
+    checks: u32,
+}
+
+/// Applique les limites par client, partagées par tous les workers.
+pub struct ClientLimiter {
+    limits: ClientLimits,
+    state: Mutex<State>,
+    clock: Arc<dyn Clock>,
+}
+
+impl ClientLimiter {
+    /// Crée un limiteur sans aucun client suivi, sur l'horloge réelle.
+    pub fn new(limits: ClientLimits) -> Self {
+        Self { limits, state: Mutex::new(State::default()), clock: Arc::new(SystemClock) }
+    }
+
+    /// Remplace l'horloge qui remplit les seaux à jetons.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Vérifie qu'une nouvelle connexion depuis `ip` est autorisée et la compte.
+    ///
+    /// # Returns
+    ///
+    /// Un `ClientGuard` à garder pendant toute la connexion, ou la raison du refus.
+    pub fn admit(self: &Arc<Self>, ip: IpAddr) -> Result<ClientGuard, Termination> {
+        let now = self.clock.now();
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+
+        state.checks += 1;
+        if state.checks >= SWEEP_INTERVAL {
+            state.checks = 0;
+            self.sweep(state, now);
+        }
+
+        if let Some(max) = self.limits.max_conns_per_ip {
+            if state.active.get(&ip).copied().unwrap_or(0) >= max {
+                return Err(Termination::IpMaxConnections);
+            }
+        }
+
+        // Le préfixe est vérifié sans consommer de jeton tant que l'adresse n'est pas acceptée
+        let cidr = self.limits.cidr_rate.map(|rate| (rate, mask(ip, self.limits.cidr_v4_prefix, self.limits.cidr_v6_prefix)));
+        if let Some((rate, network)) = &cidr {
+            let bucket = state.cidr_buckets.entry(*network).or_insert_with(|| Bucket::new(rate, now));
+            bucket.refill(rate, now);
+            if bucket.tokens < 1.0 {
+                return Err(Termination::RateLimited);
+            }
+        }
+        if let Some(rate) = &self.limits.ip_rate {
+            let bucket = state.ip_buckets.entry(ip).or_insert_with(|| Bucket::new(rate, now));
+            if !bucket.take(rate, now) {
+                return Err(Termination::RateLimited);
+            }
+        }
+        if let Some((rate, network)) = &cidr {
+            state.cidr_buckets.get_mut(network).unwrap().take(rate, now);
+        }
+
+        if self.limits.max_conns_per_ip.is_some() {
+            *state.active.entry(ip).or_insert(0) += 1;
+        }
+        Ok(ClientGuard { limiter: Arc::clone(self), ip })
+    }
+
+    // Oublie les seaux pleins et les compteurs à zéro
+    fn sweep(&self, state: &mut State, now: Instant) {
+        if let Some(rate) = &self.limits.ip_rate {
+            state.ip_buckets.retain(|_, bucket| !bucket.is_full(rate, now));
+        }
+        if let Some(rate) = &self.limits.cidr_rate {
+            state.cidr_buckets.retain(|_, bucket| !bucket.is_full(rate, now));
+        }
+        state.active.retain(|_, count| *count > 0);
+    }
+}
+
+/// Connexion comptée pour une adresse IP, libérée à la destruction.
+pub struct ClientGuard {
+    limiter: Arc<ClientLimiter>,
+    ip: IpAddr,
+}
+
+impl Drop for ClientGuard {
+    fn drop(&mut self) {
+        if self.limiter.limits.max_conns_per_ip.is_some() {
+            let mut state = self.limiter.state.lock().unwrap();
+            if let Some(count) = state.active.get_mut(&self.ip) {
+                *count = count.saturating_sub(1);
+            }
+        }
+    }
+}
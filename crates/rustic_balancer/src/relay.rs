@@ -0,0 +1,278 @@
+//! Relais bidirectionnel entre un client et un serveur cible, borné par des délais.
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+use crate::mirror::Mirror;
+use crate::net::Stream;
+use crate::throttle::Throttle;
+
+// Taille des buffers de chaque sens du relais
+const BUFFER_SIZE: usize = 16 * 1024;
+
+/// Délais appliqués à chaque connexion. `None` désactive le délai correspondant.
+#[derive(Clone, Copy, Debug)]
+pub struct Timeouts {
+    /// Délai d'établissement de la connexion vers le serveur cible.
+    pub connect: Option<Duration>,
+    /// Délai avant le premier octet envoyé par le client.
+    pub first_byte: Option<Duration>,
+    /// Délai maximal sans aucun trafic dans un sens ou dans l'autre.
+    pub idle: Option<Duration>,
+    /// Durée de vie maximale d'une connexion.
+    pub lifetime: Option<Duration>,
+}
+
+/// Traitements facultatifs des octets relayés.
+#[derive(Default)]
+pub struct Shaping {
+    /// Copie des octets du client vers un serveur fantôme.
+    pub mirror: Option<Mirror>,
+    /// Débits maximaux des octets envoyés par le client, tous respectés.
+    pub upload: Vec<Arc<Throttle>>,
+    /// Débits maximaux des octets envoyés par le serveur cible, tous respectés.
+    pub download: Vec<Arc<Throttle>>,
+    /// Ferme la connexion après ce nombre d'octets relayés, dans les deux sens confondus.
+    pub drop_after: Option<u64>,
+}
+
+/// Raison de la fin d'une connexion relayée.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Termination {
+    /// Les deux côtés ont fermé la connexion normalement.
+    Completed,
+    /// Le serveur cible n'a pas accepté la connexion à temps.
+    ConnectTimeout,
+    /// La connexion vers le serveur cible a échoué.
+    ConnectError,
+    /// Le client n'a rien envoyé à temps.
+    FirstByteTimeout,
+    /// Aucun trafic pendant le délai d'inactivité.
+    IdleTimeout,
+    /// La connexion a dépassé sa durée de vie maximale.
+    LifetimeExceeded,
+    /// Erreur de lecture ou d'écriture côté client.
+    ClientError,
+    /// Erreur de lecture ou d'écriture côté serveur cible.
+    BackendError,
+    /// Le listener avait atteint son nombre maximal de connexions.
+    MaxConnections,
+    /// Tous les serveurs cibles étaient pleins et la file d'attente aussi.
+    QueueFull,
+    /// Aucun serveur cible ne s'est libéré avant la fin de l'attente dans la file.
+    QueueTimeout,
+    /// Tous les serveurs cibles étaient éjectés par la détection des serveurs défaillants.
+    NoBackend,
+    /// Le client ou son réseau a dépassé son débit de nouvelles connexions.
+    RateLimited,
+    /// Le client avait atteint son nombre maximal de connexions simultanées.
+    IpMaxConnections,
+    /// L'adresse du client est refusée par les listes d'accès.
+    Denied,
+    /// Une faute injectée a répondu une erreur HTTP au client.
+    FaultAbort,
+    /// Une faute injectée a réinitialisé la connexion du client.
+    FaultReset,
+    /// Une faute injectée a coupé la connexion après quelques octets.
+    FaultDrop,
+}
+
+impl Termination {
+    /// Toutes les raisons, dans l'ordre d'affichage des métriques.
+    pub const ALL: [Termination; 18] = [
+        Termination::Completed,
+        Termination::ConnectTimeout,
+        Termination::ConnectError,
+        Termination::FirstByteTimeout,
+        Termination::IdleTimeout,
+        Termination::LifetimeExceeded,
+        Termination::ClientError,
+        Termination::BackendError,
+        Termination::MaxConnections,
+        Termination::QueueFull,
+        Termination::QueueTimeout,
+        Termination::NoBackend,
+        Termination::RateLimited,
+        Termination::IpMaxConnections,
+        Termination::Denied,
+        Termination::FaultAbort,
+        Termination::FaultReset,
+        Termination::FaultDrop,
+    ];
+
+    /// Nom utilisé dans les logs et les métriques.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Termination::Completed => "completed",
+            Termination::ConnectTimeout => "connect_timeout",
+            Termination::ConnectError => "connect_error",
+            Termination::FirstByteTimeout => "first_byte_timeout",
+            Termination::IdleTimeout => "idle_timeout",
+            Termination::LifetimeExceeded => "lifetime_exceeded",
+            Termination::ClientError => "client_error",
+            Termination::BackendError => "backend_error",
+            Termination::MaxConnections => "max_connections",
+            Termination::QueueFull => "queue_full",
+            Termination::QueueTimeout => "queue_timeout",
+            Termination::NoBackend => "no_backend",
+            Termination::RateLimited => "rate_limited",
+            Termination::IpMaxConnections => "ip_max_connections",
+            Termination::Denied => "denied",
+            Termination::FaultAbort => "fault_abort",
+            Termination::FaultReset => "fault_reset",
+            Termination::FaultDrop => "fault_drop",
+        }
+    }
+}
+
+impl fmt::Display for Termination {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Établit la connexion vers le serveur cible, `ip:port` ou `unix:<chemin>`, en respectant le
+/// délai de connexion.
+pub async fn connect(server: &str, timeouts: &Timeouts) -> Result<Stream, Termination> {
+    let connect = Stream::connect(server);
+    let result = match timeouts.connect {
+        Some(timeout) => tokio::time::timeout(timeout, connect).await.map_err(|_| Termination::ConnectTimeout)?,
+        None => connect.await,
+    };
+    result.map_err(|_| Termination::ConnectError)
+}
+
+/// Relaie les données dans les deux sens jusqu'à ce que les deux côtés aient fermé la
+/// connexion ou qu'un délai expire.
+///
+/// Tant que le client n'a rien envoyé, seul le délai du premier octet s'applique ; ensuite,
+/// le délai d'inactivité repart de zéro à chaque lecture ou écriture. La durée de vie est
+/// comptée depuis l'appel. Chaque sens avance indépendamment de l'autre, et les délais
+/// s'appliquent même si un sens attend un pair qui ne lit plus ou un débit limité. `shaping`
+/// copie les octets du client vers un serveur fantôme, limite le débit de chaque sens ou
+/// coupe la connexion après un nombre d'octets donné.
+pub async fn relay<C, S>(client: &mut C, server: &mut S, timeouts: &Timeouts, shaping: Shaping) -> Termination
+where
+    C: AsyncRead + AsyncWrite + Unpin,
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let Shaping { mirror, upload, download, drop_after } = shaping;
+    let start = Instant::now();
+    // Une échéance trop lointaine pour être représentée revient à ne pas en avoir
+    let lifetime = timeouts.lifetime.and_then(|lifetime| start.checked_add(lifetime));
+    let first_byte = timeouts.first_byte.and_then(|timeout| start.checked_add(timeout));
+    let progress = Progress {
+        last_activity: Mutex::new(start),
+        client_spoke: AtomicBool::new(false),
+        spoke: Notify::new(),
+        remaining: AtomicU64::new(drop_after.unwrap_or(u64::MAX)),
+    };
+
+    let (client_read, client_write) = tokio::io::split(client);
+    let (server_read, server_write) = tokio::io::split(server);
+    let transfer = async {
+        let upload = forward(client_read, server_write, &upload, mirror, &progress, true);
+        let download = forward(server_read, client_write, &download, None, &progress, false);
+        match tokio::try_join!(upload, download) {
+            Ok(_) => Termination::Completed,
+            Err(reason) => reason,
+        }
+    };
+
+    // Prochaine échéance d'inactivité : premier octet du client, puis inactivité
+    let next_deadline = || {
+        if progress.client_spoke.load(Ordering::Relaxed) {
+            (timeouts.idle.and_then(|idle| progress.last_activity.lock().unwrap().checked_add(idle)), Termination::IdleTimeout)
+        } else {
+            (first_byte, Termination::FirstByteTimeout)
+        }
+    };
+    let watchdog = async {
+        loop {
+            let (deadline, reason) = next_deadline();
+            tokio::select! {
+                _ = sleep_until(deadline) => {
+                    // Du trafic a pu repousser l'échéance pendant l'attente
+                    if next_deadline() == (deadline, reason) {
+                        return reason;
+                    }
+                }
+                _ = progress.spoke.notified(), if !progress.client_spoke.load(Ordering::Relaxed) => {}
+            }
+        }
+    };
+
+    tokio::select! {
+        reason = transfer => reason,
+        reason = watchdog => reason,
+        _ = sleep_until(lifetime) => Termination::LifetimeExceeded,
+    }
+}
+
+// État partagé par les deux sens d'un relais
+struct Progress {
+    last_activity: Mutex<Instant>,
+    client_spoke: AtomicBool,
+    // Réveille la surveillance des délais quand le client envoie son premier octet
+    spoke: Notify,
+    // Octets qui restent à relayer avant la coupure, dans les deux sens confondus
+    remaining: AtomicU64,
+}
+
+// Copie un sens du relais jusqu'à la fin de sa source, puis le signale à sa destination
+async fn forward<R, W>(mut from: R, mut to: W, throttles: &[Arc<Throttle>], mut mirror: Option<Mirror>, progress: &Progress, from_client: bool) -> Result<(), Termination>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let (read_error, write_error) = if from_client {
+        (Termination::ClientError, Termination::BackendError)
+    } else {
+        (Termination::BackendError, Termination::ClientError)
+    };
+    let mut buf = vec![0; BUFFER_SIZE];
+    loop {
+        let n = from.read(&mut buf).await.map_err(|_| read_error)?;
+        if from_client && !progress.client_spoke.swap(true, Ordering::Relaxed) {
+            progress.spoke.notify_one();
+        }
+        if n == 0 {
+            let _ = to.shutdown().await;
+            return Ok(());
+        }
+        *progress.last_activity.lock().unwrap() = Instant::now();
+
+        // Réserve les octets avant d'écrire, l'autre sens pouvant avancer pendant l'écriture
+        let reserved = progress.remaining.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |remaining| Some(remaining - limit(n, remaining) as u64));
+        let n = limit(n, reserved.unwrap_or_default());
+        for throttle in throttles {
+            throttle.consume(n).await;
+        }
+        if let Some(mirror) = &mut mirror {
+            mirror.send(&buf[..n]);
+        }
+        to.write_all(&buf[..n]).await.map_err(|_| write_error)?;
+        *progress.last_activity.lock().unwrap() = Instant::now();
+        if progress.remaining.load(Ordering::Relaxed) == 0 {
+            return Err(Termination::FaultDrop);
+        }
+    }
+}
+
+// Nombre d'octets lus à relayer sans dépasser ceux qui restent avant la coupure
+fn limit(read: usize, remaining: u64) -> usize {
+    read.min(usize::try_from(remaining).unwrap_or(usize::MAX))
+}
+
+// Attend l'échéance donnée, ou indéfiniment s'il n'y en a pas
+async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
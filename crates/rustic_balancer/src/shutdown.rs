@@ -0,0 +1,61 @@
+//! Arrêt propre : signaux d'arrêt, drainage des connexions en cours et code de sortie
+//! `EXIT_DRAIN_TIMEOUT` quand certaines ont dû être fermées de force.
+
+use std::future::Future;
+use std::io;
+use std::time::Duration;
+use tokio::task::JoinSet;
+
+/// Code de sortie utilisé quand des connexions ont dû être fermées de force à l'arrêt.
+pub const EXIT_DRAIN_TIMEOUT: u8 = 3;
+
+/// Installe les gestionnaires de SIGINT et SIGTERM et retourne un futur qui se termine
+/// à la réception du premier des deux.
+///
+/// Les gestionnaires sont installés dès l'appel, et non au premier `poll` du futur,
+/// pour qu'un signal reçu juste après le démarrage ne tue pas le processus.
+///
+/// # Errors
+///
+/// Retourne une erreur si les gestionnaires de signaux ne peuvent pas être installés.
+#[cfg(unix)]
+pub fn signal() -> io::Result<impl Future<Output = ()>> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut terminate = signal(SignalKind::terminate())?;
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    Ok(async move {
+        tokio::select! {
+            _ = terminate.recv() => {},
+            _ = interrupt.recv() => {},
+        }
+    })
+}
+
+/// Retourne un futur qui se termine à la réception de Ctrl + C.
+#[cfg(not(unix))]
+pub fn signal() -> io::Result<impl Future<Output = ()>> {
+    Ok(async {
+        let _ = tokio::signal::ctrl_c().await;
+    })
+}
+
+/// Laisse les connexions en cours se terminer pendant au plus `timeout`,
+/// puis ferme de force celles qui restent.
+///
+/// # Returns
+///
+/// `true` si toutes les connexions se sont terminées avant l'expiration du délai.
+pub async fn drain(connections: &mut JoinSet<()>, timeout: Duration) -> bool {
+    let drained = tokio::time::timeout(timeout, async {
+        while connections.join_next().await.is_some() {}
+    })
+    .await
+    .is_ok();
+
+    if !drained {
+        eprintln!("Drain timeout expired, closing {} connection(s)", connections.len());
+        connections.shutdown().await;
+    }
+    drained
+}
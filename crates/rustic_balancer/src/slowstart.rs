@@ -0,0 +1,64 @@
+//! Montée en charge progressive des serveurs qui arrivent dans le pool.
+//!
+//! Un serveur qui vient d'être ajouté, ou réintégré après une éjection, a souvent des caches
+//! froids. Pendant la fenêtre de démarrage lent, son poids effectif part d'une fraction de son
+//! poids configuré et remonte jusqu'à ce poids, linéairement ou selon une courbe réglée par
+//! `aggression`.
+
+use std::time::Duration;
+
+/// Réglages du démarrage lent.
+#[derive(Clone, Copy, Debug)]
+pub struct SlowStart {
+    /// Durée de la montée en charge.
+    pub window: Duration,
+    /// Forme de la courbe : `1` est linéaire, une valeur plus grande monte plus vite au début.
+    pub aggression: f64,
+    /// Fraction minimale du poids configuré, entre 0 et 1.
+    pub min_weight: f64,
+}
+
+impl SlowStart {
+    /// Lit des réglages de la forme `<secondes>[,aggression=<a>][,min_weight=<fraction>]`.
+    ///
+    /// Par défaut la montée est linéaire et part de 10 % du poids configuré.
+    ///
+    /// # Errors
+    ///
+    /// Retourne un message d'erreur si la durée ou une option est invalide.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut parts = spec.split(',');
+        let window = parts.next().unwrap_or_default().trim();
+        let window = window
+            .parse::<f64>()
+            .ok()
+            .filter(|v| v.is_finite() && *v > 0.0)
+            .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+            .ok_or_else(|| format!("invalid slow start window: {}", window))?;
+        let mut slow_start = Self { window, aggression: 1.0, min_weight: 0.1 };
+
+        for option in parts {
+            let (key, value) = option
+                .split_once('=')
+                .ok_or_else(|| format!("invalid slow start option: {}", option))?;
+            let value = value.trim();
+            let parsed = value.parse::<f64>().ok().filter(|v| v.is_finite());
+            let invalid = || format!("invalid value for {}: {}", key, value);
+            match key.trim() {
+                "aggression" => slow_start.aggression = parsed.filter(|v| *v > 0.0).ok_or_else(invalid)?,
+                "min_weight" => slow_start.min_weight = parsed.filter(|v| (0.0..=1.0).contains(v)).ok_or_else(invalid)?,
+                _ => return Err(format!("unknown slow start option: {}", key)),
+            }
+        }
+        Ok(slow_start)
+    }
+
+    /// Fraction du poids configuré accordée `elapsed` après le début de la montée en charge.
+    pub fn factor(&self, elapsed: Duration) -> f64 {
+        if elapsed >= self.window {
+            return 1.0;
+        }
+        let progress = elapsed.as_secs_f64() / self.window.as_secs_f64();
+        progress.powf(1.0 / self.aggression).max(self.min_weight)
+    }
+}
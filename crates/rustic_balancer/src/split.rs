@@ -0,0 +1,116 @@
+//! Répartition du trafic entre plusieurs pools de serveurs, pour les déploiements canari
+//! et bleu/vert.
+//!
+//! Chaque serveur appartient à un pool (`default` sans option `pool=`). La répartition donne
+//! à chaque pool un pourcentage des clients, dans l'ordre où les pools sont déclarés. Un client
+//! est placé d'après un hachage de son adresse IP, ou de son réseau IPv6 avec
+//! `--affinity-v6-prefix` : il retombe toujours du même côté, et faire passer un pool de 5 % à
+//! 10 % ne déplace que les clients nécessaires.
+//!
+//! Le hachage est un FNV-1a 64 bits sur les octets de l'adresse (4 en IPv4, 16 en IPv6), pris
+//! modulo 100 : il ne dépend ni de la version de Rust ni du processus, et un client garde donc
+//! son pool après une mise à jour ou un redémarrage du répartiteur.
+
+use std::fmt;
+use std::net::IpAddr;
+
+// Paramètres du FNV-1a 64 bits
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Part de chaque pool, en pourcentage des clients.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Split {
+    shares: Vec<(String, u32)>,
+}
+
+impl Split {
+    /// Lit une répartition de la forme `pool=pourcentage[,pool=pourcentage...]`.
+    ///
+    /// Les séparateurs `&` sont aussi acceptés, pour les paramètres d'une URL.
+    ///
+    /// # Errors
+    ///
+    /// Retourne un message d'erreur si un pourcentage est invalide ou dépasse 100, si un pool
+    /// apparaît deux fois ou si le total ne fait pas 100.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut shares: Vec<(String, u32)> = Vec::new();
+        for share in spec.split([',', '&']) {
+            let (pool, percent) = share
+                .split_once('=')
+                .ok_or_else(|| format!("invalid split: {}", share))?;
+            let pool = pool.trim();
+            let percent = percent
+                .trim()
+                .parse()
+                .ok()
+                .filter(|percent| *percent <= 100)
+                .ok_or_else(|| format!("invalid percentage: {}", percent))?;
+            if pool.is_empty() || shares.iter().any(|(name, _)| name == pool) {
+                return Err(format!("invalid pool in split: {}", share));
+            }
+            shares.push((pool.to_string(), percent));
+        }
+        let total: u32 = shares.iter().map(|(_, percent)| percent).sum();
+        if total != 100 {
+            return Err(format!("split percentages add up to {} instead of 100", total));
+        }
+        Ok(Self { shares })
+    }
+
+    /// Pools de la répartition et leur part, dans l'ordre de déclaration.
+    pub fn shares(&self) -> &[(String, u32)] {
+        &self.shares
+    }
+
+    /// Vérifie que chaque pool de la répartition fait partie de `pools`.
+    ///
+    /// # Errors
+    ///
+    /// Retourne un message d'erreur nommant le premier pool sans serveur.
+    pub fn check_pools(&self, pools: &[&str]) -> Result<(), String> {
+        match self.shares.iter().find(|(pool, _)| !pools.contains(&pool.as_str())) {
+            Some((pool, _)) => Err(format!("no server in pool: {}", pool)),
+            None => Ok(()),
+        }
+    }
+
+    /// Pool du client d'adresse `ip`, une clé d'affinité (voir `pool::affinity_key`).
+    pub fn pool_for(&self, ip: &str) -> &str {
+        let bucket = Self::bucket(ip);
+        let mut upper = 0;
+        for (pool, percent) in &self.shares {
+            upper += percent;
+            if bucket < upper {
+                return pool;
+            }
+        }
+        unreachable!("split percentages add up to 100")
+    }
+
+    /// Tranche de 0 à 99 du client d'adresse `ip` : FNV-1a 64 bits sur les octets de l'adresse,
+    /// ou sur le texte de la clé si ce n'est pas une adresse IP, modulo 100.
+    pub fn bucket(ip: &str) -> u32 {
+        let octets = match ip.parse::<IpAddr>() {
+            Ok(IpAddr::V4(ip)) => ip.octets().to_vec(),
+            Ok(IpAddr::V6(ip)) => ip.octets().to_vec(),
+            Err(_) => ip.as_bytes().to_vec(),
+        };
+        let hash = octets
+            .iter()
+            .fold(FNV_OFFSET_BASIS, |hash, octet| (hash ^ u64::from(*octet)).wrapping_mul(FNV_PRIME));
+        (hash % 100) as u32
+    }
+}
+
+impl fmt::Display for Split {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (pool, percent)) in self.shares.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{}={}", pool, percent)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,198 @@
+//! Limitation du débit en octets des flux relayés.
+//!
+//! Un `Throttle` est un seau à jetons qui peut être partagé entre plusieurs connexions :
+//! chaque envoi réserve ses octets, quitte à endetter le seau, puis attend que la dette soit
+//! remboursée. Les connexions qui partagent un seau se partagent donc son débit.
+//!
+//! Les limites se règlent dans chaque sens, pour le listener entier, pour chaque adresse IP
+//! cliente, pour chaque connexion et pour chaque serveur cible. Une connexion respecte toutes
+//! les limites qui la concernent.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+
+use crate::relay::Shaping;
+
+// Nombre de connexions entre deux nettoyages des seaux des clients partis
+const SWEEP_INTERVAL: u32 = 1024;
+
+/// Débits maximaux dans chaque sens, en octets par seconde. `None` ne limite pas le sens.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Bandwidth {
+    /// Octets envoyés par le client vers le serveur cible.
+    pub upload: Option<u64>,
+    /// Octets envoyés par le serveur cible vers le client.
+    pub download: Option<u64>,
+}
+
+impl Bandwidth {
+    /// Même débit dans les deux sens.
+    pub fn both(bytes_per_second: u64) -> Self {
+        Self { upload: Some(bytes_per_second), download: Some(bytes_per_second) }
+    }
+
+    /// Lit des débits de la forme `<débit>`, pour les deux sens, ou `upload=<débit>,download=<débit>`.
+    /// Un débit est un nombre d'octets par seconde, éventuellement suivi de `k`, `m` ou `g`
+    /// (multiples de 1024).
+    ///
+    /// # Errors
+    ///
+    /// Retourne un message d'erreur si un débit ou une option est invalide.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut bandwidth = Self::default();
+        for option in spec.split(',').map(str::trim) {
+            match option.split_once('=') {
+                None => bandwidth = Self::both(parse_rate(option)?),
+                Some((key, value)) => match key.trim() {
+                    "upload" => bandwidth.upload = Some(parse_rate(value)?),
+                    "download" => bandwidth.download = Some(parse_rate(value)?),
+                    _ => return Err(format!("unknown bandwidth option: {}", key)),
+                },
+            }
+        }
+        Ok(bandwidth)
+    }
+
+    /// Indique si au moins un sens est limité.
+    pub fn is_limited(&self) -> bool {
+        self.upload.is_some() || self.download.is_some()
+    }
+}
+
+/// Lit un débit en octets par seconde, éventuellement suivi de `k`, `m` ou `g`.
+///
+/// # Errors
+///
+/// Retourne un message d'erreur si le débit n'est pas un nombre strictement positif.
+pub fn parse_rate(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let (digits, unit) = match value.char_indices().last() {
+        Some((i, 'k' | 'K')) => (&value[..i], 1 << 10),
+        Some((i, 'm' | 'M')) => (&value[..i], 1 << 20),
+        Some((i, 'g' | 'G')) => (&value[..i], 1 << 30),
+        _ => (value, 1),
+    };
+    digits
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(unit))
+        .filter(|n| *n > 0)
+        .ok_or_else(|| format!("invalid bandwidth: {}", value))
+}
+
+/// Seau à jetons en octets par seconde.
+#[derive(Debug)]
+pub struct Throttle {
+    bytes_per_second: f64,
+    burst: f64,
+    // Jetons disponibles (négatifs quand le seau est endetté) et instant du dernier remplissage
+    state: Mutex<(f64, Instant)>,
+}
+
+impl Throttle {
+    /// Crée un seau plein, qui laisse passer une seconde de débit d'un coup.
+    pub fn new(bytes_per_second: u64) -> Self {
+        let rate = bytes_per_second as f64;
+        Self { bytes_per_second: rate, burst: rate, state: Mutex::new((rate, Instant::now())) }
+    }
+
+    /// Débit autorisé, en octets par seconde.
+    pub fn bytes_per_second(&self) -> u64 {
+        self.bytes_per_second as u64
+    }
+
+    /// Réserve `bytes` octets et attend, si nécessaire, que le seau les ait couverts.
+    pub async fn consume(&self, bytes: usize) {
+        let wait = self.reserve(bytes);
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    // Retire les octets du seau et retourne le temps nécessaire pour rembourser la dette
+    fn reserve(&self, bytes: usize) -> Duration {
+        let mut state = self.state.lock().unwrap();
+        let (tokens, last) = &mut *state;
+        let now = Instant::now();
+        *tokens = (*tokens + now.duration_since(*last).as_secs_f64() * self.bytes_per_second).min(self.burst);
+        *last = now;
+        *tokens -= bytes as f64;
+        if *tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-*tokens / self.bytes_per_second)
+        }
+    }
+}
+
+/// Seaux des deux sens d'une limite, partagés par toutes les connexions qui la respectent.
+#[derive(Clone, Debug, Default)]
+pub struct Throttles {
+    upload: Option<Arc<Throttle>>,
+    download: Option<Arc<Throttle>>,
+}
+
+impl Throttles {
+    /// Crée les seaux des sens limités.
+    pub fn new(bandwidth: Bandwidth) -> Self {
+        Self {
+            upload: bandwidth.upload.map(|rate| Arc::new(Throttle::new(rate))),
+            download: bandwidth.download.map(|rate| Arc::new(Throttle::new(rate))),
+        }
+    }
+
+    /// Soumet une connexion à ces seaux.
+    pub fn apply(&self, shaping: &mut Shaping) {
+        shaping.upload.extend(self.upload.clone());
+        shaping.download.extend(self.download.clone());
+    }
+
+    // Indique si une connexion utilise encore ces seaux
+    fn in_use(&self) -> bool {
+        self.upload.iter().chain(&self.download).any(|throttle| Arc::strong_count(throttle) > 1)
+    }
+}
+
+/// Limites de débit du listener, des clients et des connexions, partagées par tous les workers.
+pub struct BandwidthLimits {
+    listener: Throttles,
+    client: Bandwidth,
+    connection: Bandwidth,
+    // Seaux de chaque adresse IP cliente et nombre de connexions depuis le dernier nettoyage
+    clients: Mutex<(HashMap<IpAddr, Throttles>, u32)>,
+}
+
+impl BandwidthLimits {
+    /// Limite le débit total du listener, celui de chaque adresse IP cliente, toutes ses
+    /// connexions confondues, et celui de chaque connexion.
+    pub fn new(listener: Bandwidth, client: Bandwidth, connection: Bandwidth) -> Self {
+        Self {
+            listener: Throttles::new(listener),
+            client,
+            connection,
+            clients: Mutex::new((HashMap::new(), 0)),
+        }
+    }
+
+    /// Soumet une nouvelle connexion du client `ip` aux limites du listener, du client et de la connexion.
+    pub fn apply(&self, ip: IpAddr, shaping: &mut Shaping) {
+        self.listener.apply(shaping);
+        if self.client.is_limited() {
+            let mut clients = self.clients.lock().unwrap();
+            let (throttles, checks) = &mut *clients;
+            *checks += 1;
+            if *checks >= SWEEP_INTERVAL {
+                // Oublie les clients qui n'ont plus de connexion
+                *checks = 0;
+                throttles.retain(|_, throttles| throttles.in_use());
+            }
+            throttles.entry(ip).or_insert_with(|| Throttles::new(self.client)).apply(shaping);
+        }
+        if self.connection.is_limited() {
+            Throttles::new(self.connection).apply(shaping);
+        }
+    }
+}
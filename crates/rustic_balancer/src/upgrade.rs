@@ -0,0 +1,194 @@
+//! Mise à jour à chaud du load balancer sans refuser de connexion.
+//!
+//! À la réception de SIGUSR2, le processus relance son propre exécutable avec les mêmes
+//! arguments en lui transmettant les descripteurs de ses sockets d'écoute via la variable
+//! d'environnement `RB_LISTEN_FD`. Le nouveau processus reprend ces sockets au lieu d'en
+//! lier de nouveaux, puis envoie SIGTERM à son parent dès qu'il accepte des connexions :
+//! l'ancien processus suit alors le chemin d'arrêt habituel et draine ses connexions.
+//! Les sockets ne sont jamais fermés pendant l'échange, les clients ne voient donc aucun refus.
+//! Les sockets TCP comme les sockets Unix sont transmis.
+
+use std::io;
+
+use crate::net::ListenSocket;
+
+/// Variable d'environnement portant les descripteurs des sockets d'écoute hérités, séparés par des virgules.
+pub const LISTEN_FD_ENV: &str = "RB_LISTEN_FD";
+
+/// Variable d'environnement portant le PID du processus qui a transmis le socket.
+pub const PARENT_PID_ENV: &str = "RB_PARENT_PID";
+
+/// Variables d'environnement d'une mise à jour à chaud, lues au démarrage du processus.
+#[derive(Debug, Default)]
+pub struct HandoffEnv {
+    listen_fds: Option<String>,
+    parent_pid: Option<String>,
+}
+
+impl HandoffEnv {
+    /// Lit et retire `RB_LISTEN_FD` et `RB_PARENT_PID`, pour ne pas les transmettre aux
+    /// processus lancés ensuite.
+    ///
+    /// Modifier l'environnement n'est pas sûr pendant que d'autres threads le lisent : cette
+    /// fonction doit être appelée avant de démarrer le runtime.
+    pub fn take() -> Self {
+        Self { listen_fds: take_env(LISTEN_FD_ENV), parent_pid: take_env(PARENT_PID_ENV) }
+    }
+}
+
+/// Sockets d'écoute transmis par le processus précédent.
+pub struct Handoff {
+    pub listeners: Vec<ListenSocket>,
+    #[cfg_attr(not(unix), allow(dead_code))]
+    parent_pid: i32,
+}
+
+impl Handoff {
+    /// Reprend les sockets d'écoute transmis par le processus précédent, s'il y en a, d'après
+    /// les variables lues par `HandoffEnv::take`.
+    ///
+    /// # Errors
+    ///
+    /// Retourne une erreur si `RB_LISTEN_FD` ou `RB_PARENT_PID` ne sont pas valides.
+    #[cfg(unix)]
+    pub fn inherited(env: HandoffEnv) -> io::Result<Option<Self>> {
+        use std::os::fd::{FromRawFd, RawFd};
+
+        let Some(fds) = env.listen_fds else {
+            return Ok(None);
+        };
+        let parent_pid = env
+            .parent_pid
+            .and_then(|pid| pid.parse().ok())
+            .ok_or_else(|| invalid_env(PARENT_PID_ENV))?;
+
+        let mut listeners = Vec::new();
+        for fd in fds.split(',') {
+            let fd: RawFd = fd.parse().map_err(|_| invalid_env(LISTEN_FD_ENV))?;
+
+            // Vérifie que le descripteur est bien ouvert avant d'en prendre possession
+            if unsafe { libc::fcntl(fd, libc::F_GETFD) } == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            let listener = if is_unix_socket(fd)? {
+                ListenSocket::Unix(unsafe { std::os::unix::net::UnixListener::from_raw_fd(fd) })
+            } else {
+                ListenSocket::Tcp(unsafe { std::net::TcpListener::from_raw_fd(fd) })
+            };
+            listeners.push(listener);
+        }
+        Ok(Some(Self { listeners, parent_pid }))
+    }
+
+    #[cfg(not(unix))]
+    pub fn inherited(_env: HandoffEnv) -> io::Result<Option<Self>> {
+        Ok(None)
+    }
+
+    /// Prévient le processus précédent qu'il peut s'arrêter, celui-ci acceptant désormais les connexions.
+    ///
+    /// # Errors
+    ///
+    /// Retourne une erreur si le signal ne peut pas être envoyé.
+    pub fn notify_parent(&self) -> io::Result<()> {
+        #[cfg(unix)]
+        if unsafe { libc::kill(self.parent_pid, libc::SIGTERM) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+// Lit et retire une variable d'environnement
+fn take_env(name: &str) -> Option<String> {
+    let value = std::env::var(name).ok();
+    std::env::remove_var(name);
+    value
+}
+
+// Indique si le descripteur est un socket Unix plutôt qu'un socket TCP
+#[cfg(unix)]
+fn is_unix_socket(fd: std::os::fd::RawFd) -> io::Result<bool> {
+    let mut addr: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+    let mut len = std::mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+    if unsafe { libc::getsockname(fd, std::ptr::addr_of_mut!(addr).cast(), &mut len) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(i32::from(addr.ss_family) == libc::AF_UNIX)
+}
+
+#[cfg(unix)]
+fn invalid_env(name: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, format!("invalid or missing {}", name))
+}
+
+/// Lance le processus qui succède à celui-ci en lui transmettant les sockets d'écoute.
+///
+/// # Returns
+///
+/// Le nouveau processus, pour savoir s'il s'est arrêté avant d'avoir pris la main.
+///
+/// # Errors
+///
+/// Retourne une erreur si l'exécutable courant ne peut pas être relancé.
+#[cfg(unix)]
+pub fn spawn_successor(listeners: &[ListenSocket]) -> io::Result<std::process::Child> {
+    use std::os::fd::AsRawFd;
+    use std::os::unix::process::CommandExt;
+
+    let fds: Vec<_> = listeners.iter().map(|listener| listener.as_raw_fd()).collect();
+    let list: Vec<String> = fds.iter().map(|fd| fd.to_string()).collect();
+    let mut command = std::process::Command::new(std::env::current_exe()?);
+    command
+        .args(std::env::args_os().skip(1))
+        .env(LISTEN_FD_ENV, list.join(","))
+        .env(PARENT_PID_ENV, std::process::id().to_string());
+
+    // Les sockets sont ouverts avec FD_CLOEXEC : on retire ce drapeau dans l'enfant juste avant `exec`
+    unsafe {
+        command.pre_exec(move || {
+            for &fd in &fds {
+                let flags = libc::fcntl(fd, libc::F_GETFD);
+                if flags == -1 || libc::fcntl(fd, libc::F_SETFD, flags & !libc::FD_CLOEXEC) == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            Ok(())
+        });
+    }
+
+    command.spawn()
+}
+
+#[cfg(not(unix))]
+pub fn spawn_successor(_listeners: &[ListenSocket]) -> io::Result<std::process::Child> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "hot upgrade requires Unix"))
+}
+
+/// Demandes de mise à jour à chaud (SIGUSR2).
+pub struct UpgradeSignal {
+    #[cfg(unix)]
+    signal: tokio::signal::unix::Signal,
+}
+
+impl UpgradeSignal {
+    /// Installe le gestionnaire de SIGUSR2.
+    ///
+    /// # Errors
+    ///
+    /// Retourne une erreur si le gestionnaire ne peut pas être installé.
+    pub fn new() -> io::Result<Self> {
+        Ok(Self {
+            #[cfg(unix)]
+            signal: tokio::signal::unix::signal(tokio::signal::unix::SignalKind::user_defined2())?,
+        })
+    }
+
+    /// Attend la prochaine demande de mise à jour. Ne se termine jamais hors Unix.
+    pub async fn recv(&mut self) {
+        #[cfg(unix)]
+        self.signal.recv().await;
+        #[cfg(not(unix))]
+        std::future::pending::<()>().await;
+    }
+}
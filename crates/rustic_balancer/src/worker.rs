@@ -0,0 +1,145 @@
+//! Workers d'acceptation.
+//!
+//! Par défaut un seul socket d'écoute est servi par le runtime principal. Avec `--workers N`,
+//! N sockets sont liés sur la même adresse avec `SO_REUSEPORT` et le noyau répartit les
+//! nouvelles connexions entre eux. Chaque worker peut aussi tourner dans son propre thread
+//! avec un runtime Tokio mono-thread, éventuellement épinglé sur un CPU. Le cache reste
+//! partagé entre tous les workers.
+
+use std::future::Future;
+use std::io;
+use tokio::net::TcpSocket;
+use tokio::sync::oneshot;
+
+/// Lie `count` sockets d'écoute sur `addr`.
+///
+/// Avec plusieurs sockets, ils sont tous liés avec `SO_REUSEPORT` sur le port obtenu par le
+/// premier, ce qui permet aussi d'utiliser le port `0`. Sur `[::]`, les sockets acceptent
+/// aussi les connexions IPv4, quel que soit le réglage par défaut du système.
+///
+/// # Errors
+///
+/// Retourne une erreur si l'adresse ne peut pas être résolue ou si un socket ne peut pas être lié.
+pub async fn bind(addr: &str, count: usize) -> io::Result<Vec<std::net::TcpListener>> {
+    let mut addr = tokio::net::lookup_host(addr)
+        .await?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "listen address did not resolve"))?;
+
+    let mut listeners = Vec::with_capacity(count);
+    for _ in 0..count.max(1) {
+        let socket = if addr.is_ipv4() { TcpSocket::new_v4()? } else { TcpSocket::new_v6()? };
+        socket.set_reuseaddr(true)?;
+        #[cfg(unix)]
+        if count > 1 {
+            socket.set_reuseport(true)?;
+        }
+        if addr.is_ipv6() && addr.ip().is_unspecified() {
+            set_dual_stack(&socket)?;
+        }
+        socket.bind(addr)?;
+        let listener = socket.listen(1024)?;
+
+        // Les sockets suivants réutilisent le port effectivement attribué
+        addr = listener.local_addr()?;
+        listeners.push(listener.into_std()?);
+    }
+    Ok(listeners)
+}
+
+// Désactive `IPV6_V6ONLY` pour que le socket IPv6 accepte aussi les clients IPv4
+#[cfg(unix)]
+fn set_dual_stack(socket: &TcpSocket) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    let off: libc::c_int = 0;
+    let result = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            libc::IPPROTO_IPV6,
+            libc::IPV6_V6ONLY,
+            std::ptr::addr_of!(off).cast(),
+            std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+    if result == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_dual_stack(_socket: &TcpSocket) -> io::Result<()> {
+    Ok(())
+}
+
+/// Démarre un worker dans un thread dédié, avec son propre runtime Tokio mono-thread.
+///
+/// # Arguments
+///
+/// * `index` - Le numéro du worker, utilisé pour nommer le thread et choisir le CPU.
+/// * `pin` - Épingle le thread sur le CPU de rang `index`, modulo leur nombre, parmi ceux
+///   autorisés pour le processus.
+/// * `serve` - Construit, dans le runtime du worker, le futur qui sert les connexions.
+///
+/// # Returns
+///
+/// Un futur qui se termine avec le résultat de `serve` quand le worker s'arrête.
+pub fn spawn_runtime<F, Fut, T>(index: usize, pin: bool, serve: F) -> impl Future<Output = io::Result<T>>
+where
+    F: FnOnce() -> Fut + Send + 'static,
+    Fut: Future<Output = io::Result<T>>,
+    T: Send + 'static,
+{
+    let (tx, rx) = oneshot::channel();
+    let spawned = std::thread::Builder::new()
+        .name(format!("worker-{}", index))
+        .spawn(move || {
+            if pin {
+                if let Err(e) = pin_to_cpu(index) {
+                    eprintln!("Failed to pin worker {} to a CPU: {}", index, e);
+                }
+            }
+            let result = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .and_then(|runtime| runtime.block_on(serve()));
+            let _ = tx.send(result);
+        });
+
+    async move {
+        spawned?;
+        rx.await
+            .unwrap_or_else(|_| Err(io::Error::other(format!("worker {} panicked", index))))
+    }
+}
+
+// Restreint le thread courant au CPU de rang `index`, modulo leur nombre, parmi ceux que le
+// processus a le droit d'utiliser : sous un cpuset ou `taskset`, ce ne sont pas forcément les
+// premiers CPU de la machine
+#[cfg(target_os = "linux")]
+fn pin_to_cpu(index: usize) -> io::Result<()> {
+    let size = std::mem::size_of::<libc::cpu_set_t>();
+    unsafe {
+        let mut allowed: libc::cpu_set_t = std::mem::zeroed();
+        if libc::sched_getaffinity(0, size, &mut allowed) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let cpus: Vec<usize> = (0..libc::CPU_SETSIZE as usize).filter(|&cpu| libc::CPU_ISSET(cpu, &allowed)).collect();
+        if cpus.is_empty() {
+            return Err(io::Error::other("no CPU allowed"));
+        }
+
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_SET(cpus[index % cpus.len()], &mut set);
+        if libc::sched_setaffinity(0, size, &set) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn pin_to_cpu(_index: usize) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "CPU pinning requires Linux"))
+}
@@ -0,0 +1,24 @@
+use std::process::ExitCode;
+use tokio::net::TcpListener;
+
+use rb_test_backends::echo::{self, Options};
+
+// Démarre un environnement d'exécution asynchrone pour permettre au serveur de fonctionner
+#[tokio::main]
+async fn main() -> tokio::io::Result<ExitCode> {
+    // Lit le comportement du serveur depuis la ligne de commande
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            return Ok(ExitCode::from(2));
+        }
+    };
+
+    // Lie le serveur TCP à l'adresse demandée et wait
+    let listener = TcpListener::bind(&options.listen).await?;
+    println!("Server running on {}", listener.local_addr()?);
+
+    echo::serve(listener, options.behavior).await?;
+    Ok(ExitCode::SUCCESS)
+}
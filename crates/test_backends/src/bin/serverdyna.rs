@@ -0,0 +1,254 @@
+use std::env;
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::process::ExitCode;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::watch;
+use tokio::task::JoinSet;
+
+use rustic_balancer::net;
+use rustic_balancer::shutdown::{self, EXIT_DRAIN_TIMEOUT};
+
+// Délai par défaut laissé aux connexions en cours lors de l'arrêt
+const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Gère la connexion d'un client, enregistre les détails de la connexion et envoie une réponse.
+///
+/// Cette fonction est exécutée de manière asynchrone pour chaque client connecté.
+///
+/// # Arguments
+///
+/// * `socket` - Un objet `TcpStream` représentant la connexion du client.
+/// * `user` - Une `String` représentant le nom de l'utilisateur.
+/// * `ip` - Une `String` représentant l'adresse IP du client.
+/// * `server` - Une `String` représentant l'adresse du serveur.
+///
+/// # Examples
+///
+/// ```
+/// tokio::spawn(handle_client(socket, "user1".to_string(), "127.0.0.1".to_string(), "127.0.0.1:8080".to_string()));
+/// ```
+///
+/// # Panics
+///
+/// Cette fonction ne devrait pas paniquer dans des conditions normales d'utilisation.
+///
+/// # Errors
+///
+/// Cette fonction enregistre les erreurs dans la sortie standard d'erreurs (`stderr`) lorsqu'elles se produisent.
+async fn handle_client(mut socket: TcpStream, user: String, ip: String, server: String) {
+    println!("Nouvelle connexion établie avec l'utilisateur '{}' depuis l'adresse IP '{}' sur le serveur '{}'.", user, ip, server);
+
+    // Ouverture du fichier de logs
+    let mut log_file = match OpenOptions::new().create(true).append(true).open("log.txt") {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("Erreur lors de l'ouverture du fichier de logs : {}", e);
+            return;
+        }
+    };
+
+    // Écriture des détails de la connexion dans le fichier de logs
+    if let Err(e) = writeln!(log_file, "Nouvelle connexion de l'utilisateur '{}' depuis l'adresse IP '{}' sur le serveur '{}'.", user, ip, server) {
+        eprintln!("Erreur lors de l'écriture dans le fichier de logs : {}", e);
+        return;
+    }
+
+    // Envoyer la réponse "hello world" au client
+    if let Err(e) = socket.write_all(b"hello world").await {
+        eprintln!("Erreur d'écriture pour le client '{}': {}", user, e);
+    } else {
+        println!("Réponse 'hello world' envoyée au client '{}'.", user);
+    }
+
+    // Fermer la connexion
+    if let Err(e) = socket.shutdown().await {
+        eprintln!("Erreur lors de la fermeture de la connexion pour le client '{}': {}", user, e);
+    } else {
+        println!("Client '{}' déconnecté.", user);
+    }
+}
+
+
+
+/// Lit le délai de drainage passé avec `--drain-timeout <secondes>`.
+///
+/// # Returns
+///
+/// Le délai demandé, ou `DEFAULT_DRAIN_TIMEOUT` si l'option est absente.
+///
+/// # Errors
+///
+/// Cette fonction retourne une erreur si l'option n'a pas de valeur ou si la valeur n'est pas un nombre positif.
+fn drain_timeout_from_args() -> Result<Duration, String> {
+    let mut args = env::args().skip(1);
+    let mut timeout = DEFAULT_DRAIN_TIMEOUT;
+    while let Some(arg) = args.next() {
+        if arg != "--drain-timeout" {
+            return Err(format!("Option inconnue : {}", arg));
+        }
+        let value = args.next().ok_or("Valeur manquante pour --drain-timeout")?;
+        timeout = value
+            .parse::<f64>()
+            .ok()
+            .filter(|secs| secs.is_finite() && *secs >= 0.0)
+            .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+            .ok_or_else(|| format!("Durée invalide : {}", value))?;
+    }
+    Ok(timeout)
+}
+
+/// Accepte les connexions d'un listener jusqu'à la demande d'arrêt, puis laisse
+/// les connexions en cours se terminer pendant au plus `drain_timeout`.
+///
+/// # Arguments
+///
+/// * `listener` - Le listener TCP du serveur.
+/// * `ip` - Une `String` représentant l'adresse IP du serveur.
+/// * `addr` - Une `String` représentant l'adresse complète du serveur.
+/// * `stop` - Un `watch::Receiver` qui passe à `true` quand l'arrêt est demandé.
+/// * `drain_timeout` - Le délai laissé aux connexions en cours.
+///
+/// # Returns
+///
+/// `true` si toutes les connexions se sont terminées avant l'expiration du délai.
+async fn serve(listener: TcpListener, ip: String, addr: String, mut stop: watch::Receiver<bool>, drain_timeout: Duration) -> bool {
+    let user = "utilisateur inconnu".to_string(); // À remplacer par le nom d'utilisateur approprié
+    let mut clients = JoinSet::new();
+
+    loop {
+        tokio::select! {
+            result = listener.accept() => {
+                match result {
+                    Ok((socket, _)) => {
+                        clients.spawn(handle_client(socket, user.clone(), ip.clone(), addr.clone()));
+                    },
+                    Err(e) => eprintln!("Erreur lors de l'acceptation de la connexion: {}", e),
+                }
+            }
+            // Libère les tâches des clients terminés
+            Some(_) = clients.join_next(), if !clients.is_empty() => {}
+            _ = stop.wait_for(|stop| *stop) => break,
+        }
+    }
+
+    // Arrête d'accepter immédiatement, puis draine les connexions en cours
+    drop(listener);
+    println!("Arrêt du serveur {} : {} connexion(s) en cours.", addr, clients.len());
+    shutdown::drain(&mut clients, drain_timeout).await
+}
+
+/// Point d'entrée principal de l'application. Lit les adresses du fichier `conf.txt`, une par
+/// ligne (`127.0.0.1:9000`, `[::1]:9000` ou `localhost:9000`), démarre les serveurs et gère les
+/// connexions entrantes.
+///
+/// À la réception de SIGINT ou SIGTERM, tous les serveurs arrêtent immédiatement d'accepter
+/// de nouvelles connexions. Les connexions en cours disposent du délai passé avec
+/// `--drain-timeout <secondes>` (30 secondes par défaut) pour se terminer, après quoi elles sont fermées.
+///
+/// # Returns
+///
+/// `Result<ExitCode, Box<dyn std::error::Error>>` - Le code de sortie : `0` si toutes les connexions
+/// se sont terminées à temps, `3` si certaines ont dû être fermées de force, `2` si les arguments sont invalides.
+///
+/// # Examples
+///
+/// ```sh
+/// cargo run --bin serverdyna -- --drain-timeout 10
+/// ```
+///
+/// # Panics
+///
+/// Cette fonction ne devrait pas paniquer dans des conditions normales d'utilisation.
+///
+/// # Errors
+///
+/// Cette fonction retourne une erreur si elle échoue à ouvrir le fichier `conf.txt`,
+/// à installer les gestionnaires de signaux ou à lier les listeners TCP.
+///
+/// # Tokio
+///
+/// Cette fonction utilise l'attribut `#[tokio::main]` pour indiquer qu'elle est le point d'entrée
+/// d'une application Tokio asynchrone.
+#[tokio::main]
+async fn main() -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let drain_timeout = match drain_timeout_from_args() {
+        Ok(timeout) => timeout,
+        Err(e) => {
+            eprintln!("{}", e);
+            return Ok(ExitCode::from(2));
+        }
+    };
+
+    // Installe les gestionnaires de SIGINT et SIGTERM avant de démarrer les serveurs
+    let stop_signal = shutdown::signal()?;
+
+    // Récupération du répertoire de travail actuel
+    if let Ok(current_dir) = env::current_dir() {
+        println!("Répertoire actuel : {:?}", current_dir);
+    } else {
+        println!("Impossible de récupérer le répertoire actuel.");
+    }
+
+    // Canal partagé par les serveurs pour signaler l'arrêt
+    let (stop_tx, stop_rx) = watch::channel(false);
+
+    let mut tasks = Vec::new();
+
+    // Lecture du fichier conf.txt
+    let file = std::fs::File::open("conf.txt")?;
+    let reader = BufReader::new(file);
+
+    // Boucle de lecture du fichier et démarrage des serveurs. Chaque ligne non vide est une
+    // adresse `ip:port`, `[ipv6]:port` ou `nom:port`
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        // Refuse les adresses que le load balancer refuse, comme une adresse IPv6 sans crochets
+        if let Err(e) = net::check_addr(line) {
+            eprintln!("La ligne '{}' n'est pas valide : {}", line, e);
+            continue;
+        }
+        let socket_addr = match tokio::net::lookup_host(line).await {
+            Ok(mut addrs) => addrs.next(),
+            Err(e) => {
+                eprintln!("La ligne '{}' n'est pas valide : {}", line, e);
+                continue;
+            }
+        };
+        let Some(socket_addr) = socket_addr else {
+            eprintln!("L'adresse '{}' ne correspond à aucune adresse IP.", line);
+            continue;
+        };
+
+        let listener = TcpListener::bind(socket_addr).await?;
+        println!("Serveur démarré sur {}", socket_addr);
+
+        // Boucle d'écoute des connexions
+        let ip = socket_addr.ip().to_string();
+        tasks.push(tokio::spawn(serve(listener, ip, socket_addr.to_string(), stop_rx.clone(), drain_timeout)));
+    }
+
+    // Attendre le signal d'arrêt puis prévenir tous les serveurs
+    stop_signal.await;
+    println!("Signal d'arrêt reçu. Arrêt des serveurs...");
+    let _ = stop_tx.send(true);
+
+    // Attendre que toutes les tâches se terminent
+    let mut drained = true;
+    for task in tasks {
+        drained &= task.await?;
+    }
+
+    if drained {
+        Ok(ExitCode::SUCCESS)
+    } else {
+        Ok(ExitCode::from(EXIT_DRAIN_TIMEOUT))
+    }
+}
@@ -2,6 +2,8 @@ use std::fs::File;
 use std::io::{self, BufRead};
 use std::path::Path;
 
+use rustic_balancer::SERVERS;
+
 /// Point d'entrée principal de l'application. Cette fonction lit les adresses des serveurs
 /// à partir du fichier `conf.txt` et les affiche. Elle inclut également des adresses de serveurs initiales par défaut.
 ///
@@ -14,18 +16,11 @@ use std::path::Path;
 /// ```rust
 /// fn main() -> io::Result<()> {
 ///     // Définit les adresses des serveurs initiales
-///     let initial_servers: [&str; 2] = ["127.0.0.1:8080", "127.0.0.1:8081"];
-///     
+///     let mut servers: Vec<String> = SERVERS.iter().map(|s| s.to_string()).collect();
+///
 ///     // Lire les adresses des serveurs à partir du fichier conf.txt
-///     let mut servers = Vec::new();
-///     servers.extend_from_slice(&initial_servers);
-///     
 ///     if let Ok(lines) = read_lines("conf.txt") {
-///         for line in lines {
-///             if let Ok(ip) = line {
-///                 servers.push(ip);
-///             }
-///         }
+///         servers.extend(lines.map_while(Result::ok));
 ///     }
 ///
 ///     // Affiche les serveurs pour vérifier
@@ -46,18 +41,11 @@ use std::path::Path;
 /// Cette fonction retourne une erreur si elle échoue à lire le fichier `conf.txt`.
 fn main() -> io::Result<()> {
     // Définit les adresses des serveurs initiales
-    let initial_servers: [&str; 2] = ["127.0.0.1:8080", "127.0.0.1:8081"];
-    
+    let mut servers: Vec<String> = SERVERS.iter().map(|s| s.to_string()).collect();
+
     // Lire les adresses des serveurs à partir du fichier conf.txt
-    let mut servers = Vec::new();
-    servers.extend_from_slice(&initial_servers);
-    
     if let Ok(lines) = read_lines("conf.txt") {
-        for line in lines {
-            if let Ok(ip) = line {
-                servers.push(ip);
-            }
-        }
+        servers.extend(lines.map_while(Result::ok));
     }
 
     // Affiche les serveurs pour vérifier
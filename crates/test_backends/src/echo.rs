@@ -0,0 +1,253 @@
+//! Serveur de test configurable, utilisé pour simuler des serveurs cibles réalistes ou défaillants.
+//!
+//! Le serveur répond à chaque lecture selon son `Behavior` : réponse fixe, écho des octets reçus
+//! ou sa propre adresse, après une latence tirée d'une distribution. Il peut aussi répondre une
+//! erreur ou réinitialiser la connexion au hasard, et fermer la connexion après quelques réponses.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use rand::Rng;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinSet;
+
+// Adresse d'écoute par défaut du serveur
+const DEFAULT_LISTEN: &str = "127.0.0.1:8080";
+
+// Réponse fixe par défaut
+const DEFAULT_REPLY: &[u8] = b"Coucou";
+
+/// Réponse envoyée en cas d'erreur injectée.
+pub const ERROR_REPLY: &[u8] = b"ERROR";
+
+/// Contenu des réponses du serveur.
+#[derive(Clone, Debug)]
+pub enum Reply {
+    /// Toujours la même réponse.
+    Fixed(Vec<u8>),
+    /// Renvoie les octets reçus.
+    Echo,
+    /// Répond l'adresse d'écoute du serveur, pour savoir quel serveur a servi une connexion.
+    Identify,
+}
+
+/// Distribution de la latence ajoutée avant chaque réponse.
+#[derive(Clone, Copy, Debug)]
+pub enum Latency {
+    /// Toujours la même durée.
+    Fixed(Duration),
+    /// Durée tirée uniformément entre deux bornes.
+    Uniform(Duration, Duration),
+    /// Durée tirée selon une loi exponentielle de moyenne donnée.
+    Exponential(Duration),
+}
+
+impl Latency {
+    /// Lit une latence de la forme `<secondes>`, `<min>-<max>` ou `exp:<moyenne>`.
+    ///
+    /// # Errors
+    ///
+    /// Retourne un message d'erreur si une durée est invalide ou si `min` dépasse `max`.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        if let Some(mean) = spec.strip_prefix("exp:") {
+            return Ok(Latency::Exponential(parse_secs(mean)?));
+        }
+        match spec.split_once('-') {
+            Some((min, max)) => {
+                let (min, max) = (parse_secs(min)?, parse_secs(max)?);
+                if min > max {
+                    return Err(format!("invalid latency range: {}", spec));
+                }
+                Ok(Latency::Uniform(min, max))
+            }
+            None => Ok(Latency::Fixed(parse_secs(spec)?)),
+        }
+    }
+
+    /// Tire une latence.
+    pub fn sample(&self) -> Duration {
+        let mut rng = rand::thread_rng();
+        match *self {
+            Latency::Fixed(delay) => delay,
+            Latency::Uniform(min, max) => rng.gen_range(min..=max),
+            Latency::Exponential(mean) => mean.mul_f64(-(1.0 - rng.gen::<f64>()).ln()),
+        }
+    }
+}
+
+/// Comportement du serveur pour chaque connexion.
+#[derive(Clone, Debug)]
+pub struct Behavior {
+    pub reply: Reply,
+    /// Latence ajoutée avant chaque réponse.
+    pub latency: Option<Latency>,
+    /// Probabilité de répondre `ERROR` au lieu de la réponse normale.
+    pub error_rate: f64,
+    /// Probabilité de réinitialiser la connexion au lieu de répondre.
+    pub reset_rate: f64,
+    /// Nombre de réponses après lequel le serveur ferme la connexion.
+    pub close_after: Option<usize>,
+}
+
+impl Behavior {
+    /// Serveur qui répond toujours `reply`, sans latence ni erreur.
+    pub fn fixed(reply: &[u8]) -> Self {
+        Self {
+            reply: Reply::Fixed(reply.to_vec()),
+            latency: None,
+            error_rate: 0.0,
+            reset_rate: 0.0,
+            close_after: None,
+        }
+    }
+}
+
+/// Configuration de `echo_server`, lue depuis la ligne de commande.
+///
+/// Options reconnues :
+///
+/// * `--listen <adresse>` - adresse d'écoute (par défaut `127.0.0.1:8080`).
+/// * `--reply <texte>` - répond toujours ce texte (par défaut `Coucou`).
+/// * `--echo` - renvoie les octets reçus.
+/// * `--identify` - répond l'adresse d'écoute du serveur.
+/// * `--latency <secondes>|<min>-<max>|exp:<moyenne>` - latence avant chaque réponse : fixe,
+///   uniforme entre deux bornes ou exponentielle (aucune par défaut).
+/// * `--error-rate <fraction>` - probabilité de répondre `ERROR` (0 par défaut).
+/// * `--reset-rate <fraction>` - probabilité de réinitialiser la connexion au lieu de répondre (0 par défaut).
+/// * `--close-after <n>` - ferme la connexion après `n` réponses (jamais par défaut).
+pub struct Options {
+    pub listen: String,
+    pub behavior: Behavior,
+}
+
+impl Options {
+    /// Lit la configuration depuis une liste d'arguments (sans le nom du programme).
+    ///
+    /// # Errors
+    ///
+    /// Retourne un message d'erreur si une option est inconnue, n'a pas de valeur ou a une valeur invalide.
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self, String> {
+        let mut listen = DEFAULT_LISTEN.to_string();
+        let mut behavior = Behavior::fixed(DEFAULT_REPLY);
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("missing value for {}", arg));
+            match arg.as_str() {
+                "--listen" => listen = value()?,
+                "--reply" => behavior.reply = Reply::Fixed(value()?.into_bytes()),
+                "--echo" => behavior.reply = Reply::Echo,
+                "--identify" => behavior.reply = Reply::Identify,
+                "--latency" => behavior.latency = Some(Latency::parse(&value()?)?),
+                "--error-rate" => behavior.error_rate = parse_rate(&value()?)?,
+                "--reset-rate" => behavior.reset_rate = parse_rate(&value()?)?,
+                "--close-after" => {
+                    let count = value()?.parse().ok().filter(|n| *n > 0);
+                    behavior.close_after = Some(count.ok_or_else(|| format!("invalid count for {}", arg))?);
+                }
+                _ => return Err(format!("unknown option: {}", arg)),
+            }
+        }
+        Ok(Self { listen, behavior })
+    }
+}
+
+/// Accepte les connexions du listener et répond à chaque message reçu selon `behavior`.
+///
+/// Les connexions appartiennent au serveur : abandonner la future les ferme toutes, ce qui
+/// permet de simuler la panne d'un serveur.
+///
+/// # Errors
+///
+/// Retourne une erreur si le listener ne peut plus accepter de connexion.
+pub async fn serve(listener: TcpListener, behavior: Behavior) -> tokio::io::Result<()> {
+    let local = listener.local_addr()?;
+    let behavior = Arc::new(behavior);
+    let mut connections = JoinSet::new();
+    // Boucle pour accepter les connexions
+    loop {
+        // Accepte une nouvelle connexion `socket` est utilisé pour communiquer avec le client,
+        // en libérant au passage les tâches des connexions terminées
+        let (socket, addr) = tokio::select! {
+            accepted = listener.accept() => accepted?,
+            Some(_) = connections.join_next() => continue,
+        };
+
+        // Crée une tâche asynchrone pour chaque connexion entrante pour gérer les communications
+        let behavior = Arc::clone(&behavior);
+        connections.spawn(async move {
+            // Affiche l'adresse du client connecté, msg de log console
+            println!("Received connection from: {:?}", addr);
+            handle(socket, local, &behavior).await;
+        });
+    }
+}
+
+// Répond aux messages d'un client jusqu'à la fermeture de la connexion
+async fn handle(mut socket: TcpStream, local: SocketAddr, behavior: &Behavior) {
+    // Crée un buffer pour lire les données
+    let mut buf = vec![0; 1024];
+    let mut replies = 0;
+
+    // Boucle pour lire les données envoyées par le client
+    loop {
+        let n = match socket.read(&mut buf).await {
+            Ok(0) | Err(_) => break, // Le client a fermé la connexion ou la lecture a échoué
+            Ok(n) => n,
+        };
+        if let Some(latency) = behavior.latency {
+            tokio::time::sleep(latency.sample()).await;
+        }
+
+        let (reset, error) = {
+            let mut rng = rand::thread_rng();
+            (rng.gen_bool(behavior.reset_rate), rng.gen_bool(behavior.error_rate))
+        };
+        if reset {
+            // Sans délai de linger, la fermeture envoie un RST au client
+            let _ = socket.set_zero_linger();
+            return;
+        }
+        let response = if error {
+            ERROR_REPLY.to_vec()
+        } else {
+            match &behavior.reply {
+                Reply::Fixed(reply) => reply.clone(),
+                Reply::Echo => buf[..n].to_vec(),
+                Reply::Identify => local.to_string().into_bytes(),
+            }
+        };
+        // Envoie la réponse au client. Si l'envoi échoue, imprime un message d'erreur et arrête la boucle
+        if socket.write_all(&response).await.is_err() {
+            eprintln!("Failed to send response");
+            break;
+        }
+
+        replies += 1;
+        if behavior.close_after.is_some_and(|max| replies >= max) {
+            break;
+        }
+    }
+}
+
+// Convertit un nombre de secondes (éventuellement décimal) en durée
+fn parse_secs(value: &str) -> Result<Duration, String> {
+    value
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|secs| secs.is_finite() && *secs >= 0.0)
+        .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+        .ok_or_else(|| format!("invalid duration: {}", value))
+}
+
+// Convertit une probabilité comprise entre 0 et 1
+fn parse_rate(value: &str) -> Result<f64, String> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|rate| (0.0..=1.0).contains(rate))
+        .ok_or_else(|| format!("invalid rate: {}", value))
+}
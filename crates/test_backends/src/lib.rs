@@ -0,0 +1,3 @@
+//! Serveurs cibles de test : `echo_server`, configurable, et `serverdyna`.
+
+pub mod echo;
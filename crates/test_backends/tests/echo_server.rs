@@ -0,0 +1,53 @@
+use std::net::SocketAddr;
+use std::process::Stdio;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::process::{Child, Command};
+
+// Démarre `echo_server` sur un port éphémère avec les options données
+async fn spawn_server(args: &[&str]) -> (Child, SocketAddr) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_echo_server"))
+        .args(["--listen", "127.0.0.1:0"])
+        .args(args)
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .expect("failed to start echo_server");
+    let mut lines = BufReader::new(child.stdout.take().unwrap()).lines();
+    let line = lines.next_line().await.unwrap().expect("echo_server exited before listening");
+    let addr = line.strip_prefix("Server running on ").unwrap().parse().unwrap();
+    // Continue de lire la sortie standard pour ne pas bloquer le processus
+    tokio::spawn(async move { while let Ok(Some(_)) = lines.next_line().await {} });
+    (child, addr)
+}
+
+#[tokio::test]
+async fn identifies_itself_and_closes_after_replies() {
+    let (_server, addr) = spawn_server(&["--identify", "--close-after", "2"]).await;
+
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    let mut buf = [0; 64];
+    for _ in 0..2 {
+        client.write_all(b"ping").await.unwrap();
+        let n = client.read(&mut buf).await.unwrap();
+        assert_eq!(&buf[..n], addr.to_string().as_bytes());
+    }
+    // Après deux réponses le serveur ferme la connexion
+    assert_eq!(client.read(&mut buf).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn echoes_and_injects_resets() {
+    let (_echo, addr) = spawn_server(&["--echo", "--latency", "0.01-0.02"]).await;
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    client.write_all(b"hello").await.unwrap();
+    let mut buf = [0; 16];
+    let n = client.read(&mut buf).await.unwrap();
+    assert_eq!(&buf[..n], b"hello");
+
+    let (_reset, addr) = spawn_server(&["--reset-rate", "1"]).await;
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    client.write_all(b"hello").await.unwrap();
+    let err = client.read(&mut buf).await.unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::ConnectionReset);
+}
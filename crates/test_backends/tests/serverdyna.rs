@@ -0,0 +1,35 @@
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+
+#[tokio::test]
+async fn unbracketed_ipv6_addresses_are_refused() {
+    let dir = std::env::temp_dir().join(format!("rb-serverdyna-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("conf.txt"), "::1:8080\n127.0.0.1:0\n").unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_serverdyna"))
+        .current_dir(&dir)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .expect("failed to start serverdyna");
+
+    // L'adresse sans crochets est signalée et ignorée, les suivantes sont démarrées
+    let mut errors = BufReader::new(child.stderr.take().unwrap()).lines();
+    let line = tokio::time::timeout(Duration::from_secs(5), errors.next_line()).await.unwrap().unwrap().unwrap();
+    assert!(line.starts_with("La ligne '::1:8080' n'est pas valide"), "{}", line);
+    let mut lines = BufReader::new(child.stdout.take().unwrap()).lines();
+    loop {
+        let line = lines.next_line().await.unwrap().expect("serverdyna exited before listening");
+        if line.starts_with("Serveur démarré sur 127.0.0.1:") {
+            break;
+        }
+        assert!(!line.starts_with("Serveur démarré sur [::1]"), "{}", line);
+    }
+
+    child.kill().await.unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,177 @@
+//! Banc de test en mémoire : des serveurs cibles simulés et un load balancer démarrés dans le
+//! processus du test, sur des ports éphémères.
+//!
+//! Chaque serveur simulé répond sa propre adresse, ce qui permet de savoir quel serveur a servi
+//! une connexion. Les serveurs peuvent être arrêtés puis relancés sur la même adresse pendant le test.
+//!
+//! Le load balancer suit une `ManualClock` : l'affinité des clients, les limites de débit, les
+//! éjections et le démarrage lent n'évoluent que lorsque le test avance l'horloge.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{self, AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpSocket, TcpStream};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+use rb_test_backends::echo::{self, Behavior, Reply};
+use rustic_balancer::{Address, Balancer, Config, Listener, ManualClock};
+
+// Serveur cible simulé, arrêté quand sa tâche est abandonnée
+struct Backend {
+    addr: SocketAddr,
+    behavior: Behavior,
+    task: Option<JoinHandle<io::Result<()>>>,
+}
+
+impl Backend {
+    // Démarre le serveur sur `addr`, qui peut être un port éphémère
+    async fn start(addr: SocketAddr, behavior: Behavior) -> Self {
+        let listener = TcpListener::bind(addr).await.expect("failed to bind mock backend");
+        let addr = listener.local_addr().unwrap();
+        let task = Some(tokio::spawn(echo::serve(listener, behavior.clone())));
+        Self { addr, behavior, task }
+    }
+
+    // Ferme le listener et toutes les connexions en cours
+    async fn stop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+            let _ = task.await;
+        }
+    }
+}
+
+/// Load balancer et serveurs cibles simulés, arrêtés à la fin du test.
+pub struct Harness {
+    /// Adresse d'écoute du load balancer.
+    pub addr: SocketAddr,
+    /// Load balancer, pour consulter ses métriques.
+    pub balancer: Arc<Balancer>,
+    /// Horloge du load balancer, à avancer à la main.
+    pub clock: Arc<ManualClock>,
+    backends: Vec<Backend>,
+    stop: watch::Sender<bool>,
+    running: Option<JoinHandle<io::Result<bool>>>,
+}
+
+impl Harness {
+    /// Démarre `backends` serveurs simulés et un load balancer qui les sert, avec les options données.
+    pub async fn start(backends: usize, args: &[&str]) -> Self {
+        let behavior = Behavior { reply: Reply::Identify, ..Behavior::fixed(b"") };
+        Self::start_with(vec![behavior; backends], args).await
+    }
+
+    /// Démarre un serveur simulé par comportement et un load balancer qui les sert, avec les options données.
+    ///
+    /// Seuls les serveurs qui répondent leur adresse (`Reply::Identify`) sont reconnus par `request`.
+    pub async fn start_with(behaviors: Vec<Behavior>, args: &[&str]) -> Self {
+        let mut backends = Vec::new();
+        for behavior in behaviors {
+            backends.push(Backend::start("127.0.0.1:0".parse().unwrap(), behavior).await);
+        }
+
+        let mut config_args = vec!["--listen".to_string(), "127.0.0.1:0".to_string()];
+        for backend in &backends {
+            config_args.extend(["--server".to_string(), backend.addr.to_string()]);
+        }
+        config_args.extend(args.iter().map(|arg| arg.to_string()));
+        let config = Config::parse(config_args).expect("invalid balancer options");
+
+        let listener = Listener::bind(&config.listen, config.workers).await.unwrap();
+        let Address::Tcp(addr) = listener.local_addr().unwrap() else {
+            panic!("the harness only listens on TCP");
+        };
+        let clock = Arc::new(ManualClock::new());
+        let balancer = Balancer::with_clock(config, Arc::clone(&clock) as _).expect("failed to create balancer");
+
+        let (stop, mut stop_rx) = watch::channel(false);
+        let shutdown = async move {
+            let _ = stop_rx.wait_for(|stop| *stop).await;
+        };
+        let running = Some(tokio::spawn(Arc::clone(&balancer).run(listener, shutdown)));
+        Self { addr, balancer, clock, backends, stop, running }
+    }
+
+    /// Adresse du serveur simulé d'indice `index`.
+    pub fn backend_addr(&self, index: usize) -> SocketAddr {
+        self.backends[index].addr
+    }
+
+    /// Arrête le serveur simulé d'indice `index` et ferme ses connexions.
+    pub async fn kill(&mut self, index: usize) {
+        self.backends[index].stop().await;
+    }
+
+    /// Relance le serveur simulé d'indice `index` sur la même adresse.
+    pub async fn revive(&mut self, index: usize) {
+        let backend = &mut self.backends[index];
+        backend.stop().await;
+        *backend = Backend::start(backend.addr, backend.behavior.clone()).await;
+    }
+
+    /// Ouvre une connexion au load balancer depuis l'adresse locale donnée, IPv4 ou IPv6.
+    ///
+    /// Si le load balancer écoute sur `[::]`, la connexion vise la boucle locale de la famille
+    /// de `local`.
+    pub async fn connect_from(&self, local: &str) -> TcpStream {
+        let local: IpAddr = local.parse().unwrap();
+        let socket = if local.is_ipv4() { TcpSocket::new_v4() } else { TcpSocket::new_v6() }.unwrap();
+        socket.bind(SocketAddr::new(local, 0)).unwrap();
+        let target = match self.addr.ip() {
+            ip if !ip.is_unspecified() => self.addr,
+            _ if local.is_ipv4() => SocketAddr::from((Ipv4Addr::LOCALHOST, self.addr.port())),
+            _ => SocketAddr::from((Ipv6Addr::LOCALHOST, self.addr.port())),
+        };
+        socket.connect(target).await.unwrap()
+    }
+
+    /// Envoie `ping` depuis l'adresse locale donnée et retourne l'indice du serveur qui a répondu,
+    /// ou `None` si la connexion a été fermée sans réponse reconnue.
+    pub async fn request(&self, local: &str) -> Option<usize> {
+        let mut client = self.connect_from(local).await;
+        self.served_by(&mut client).await
+    }
+
+    /// Envoie `ping` sur une connexion ouverte et retourne l'indice du serveur qui a répondu.
+    pub async fn served_by(&self, client: &mut TcpStream) -> Option<usize> {
+        let _ = client.write_all(b"ping").await;
+        let mut buf = [0; 64];
+        let n = match tokio::time::timeout(Duration::from_secs(5), client.read(&mut buf)).await {
+            Ok(Ok(n)) => n,
+            Ok(Err(_)) => 0,
+            Err(_) => panic!("connection was neither served nor closed"),
+        };
+        let reply = std::str::from_utf8(&buf[..n]).ok()?.parse::<SocketAddr>().ok()?;
+        self.backends.iter().position(|backend| backend.addr == reply)
+    }
+
+    /// Arrête le load balancer en laissant les connexions en cours se terminer, puis les
+    /// serveurs simulés. Retourne `true` si toutes les connexions se sont terminées à temps.
+    pub async fn shutdown(&mut self) -> bool {
+        let _ = self.stop.send(true);
+        let drained = match self.running.take() {
+            Some(running) => running.await.expect("balancer task panicked").expect("balancer failed"),
+            None => true,
+        };
+        for backend in &mut self.backends {
+            backend.stop().await;
+        }
+        drained
+    }
+}
+
+impl Drop for Harness {
+    // Sans appel à `shutdown`, abandonne le load balancer et les serveurs simulés
+    fn drop(&mut self) {
+        if let Some(running) = &self.running {
+            running.abort();
+        }
+        for backend in &self.backends {
+            if let Some(task) = &backend.task {
+                task.abort();
+            }
+        }
+    }
+}
@@ -0,0 +1,175 @@
+//! Outils partagés par les tests d'intégration : démarrage du binaire `load_balancer`, de
+//! serveurs cibles et de clients, et interrogation de l'interface d'administration.
+//!
+//! Le module `harness` démarre au contraire le load balancer et des serveurs simulés dans le
+//! processus du test.
+
+pub mod harness;
+
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tokio::process::{Child, Command};
+use tokio::sync::mpsc;
+
+/// Processus `load_balancer` démarré pour un test.
+pub struct Balancer {
+    pub child: Child,
+    pub addr: SocketAddr,
+    /// Lignes écrites sur la sortie standard après l'annonce de l'adresse d'écoute, et sur la sortie d'erreur.
+    pub output: mpsc::UnboundedReceiver<String>,
+}
+
+impl Balancer {
+    /// Attend une ligne de sortie commençant par `prefix` et retourne la suite de la ligne.
+    pub async fn wait_for_line(&mut self, prefix: &str) -> String {
+        loop {
+            let line = self.output.recv().await.expect("load_balancer output closed");
+            if let Some(rest) = line.strip_prefix(prefix) {
+                return rest.to_string();
+            }
+        }
+    }
+}
+
+/// Chemin du binaire `load_balancer`, construit par Cargo dans le même répertoire que les tests.
+pub fn balancer_bin() -> PathBuf {
+    let mut path = std::env::current_exe().expect("test executable path");
+    path.pop();
+    if path.ends_with("deps") {
+        path.pop();
+    }
+    path.push(format!("load_balancer{}", std::env::consts::EXE_SUFFIX));
+    path
+}
+
+/// Démarre le binaire `load_balancer` sur un port éphémère avec les options données.
+pub async fn spawn_balancer(args: &[&str]) -> Balancer {
+    let mut command = Command::new(balancer_bin());
+    command.args(["--listen", "127.0.0.1:0"]).args(args);
+    spawn_balancer_command(command).await
+}
+
+/// Démarre le load balancer avec une commande préparée par le test, par exemple lancée par un
+/// shell qui change les limites du processus. Le load balancer doit annoncer son adresse d'écoute.
+pub async fn spawn_balancer_command(mut command: Command) -> Balancer {
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .expect("failed to start load_balancer");
+
+    // Recopie la sortie d'erreur pour qu'elle reste visible, tout en la transmettant au test
+    let (tx, output) = mpsc::unbounded_channel();
+    let mut errors = BufReader::new(child.stderr.take().unwrap()).lines();
+    let errors_tx = tx.clone();
+    tokio::spawn(async move {
+        while let Ok(Some(line)) = errors.next_line().await {
+            eprintln!("{}", line);
+            let _ = errors_tx.send(line);
+        }
+    });
+
+    // Attend la ligne annonçant l'adresse d'écoute
+    let mut lines = BufReader::new(child.stdout.take().unwrap()).lines();
+    let addr = loop {
+        let line = lines.next_line().await.unwrap().expect("load_balancer exited before listening");
+        if let Some(addr) = line.strip_prefix("Load balancer running on ") {
+            break addr.parse().unwrap();
+        }
+    };
+
+    // Continue de lire la sortie standard pour ne pas bloquer le processus
+    tokio::spawn(async move {
+        while let Ok(Some(line)) = lines.next_line().await {
+            let _ = tx.send(line);
+        }
+    });
+
+    Balancer { child, addr, output }
+}
+
+/// Démarre un serveur cible qui attend `delay` après chaque lecture avant de répondre `reply`.
+pub async fn spawn_backend(reply: &'static [u8], delay: Duration) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            tokio::spawn(async move {
+                let mut buf = [0; 1024];
+                while let Ok(n) = socket.read(&mut buf).await {
+                    if n == 0 {
+                        break;
+                    }
+                    tokio::time::sleep(delay).await;
+                    if socket.write_all(reply).await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    });
+    addr
+}
+
+/// Envoie un signal Unix au processus donné.
+pub fn send_signal(pid: u32, signal: &str) {
+    let status = std::process::Command::new("kill")
+        .args(["-s", signal, &pid.to_string()])
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+/// Envoie une requête `GET` à l'interface d'administration et retourne le corps de la réponse.
+pub async fn admin_get(addr: &str, path: &str) -> String {
+    admin_request(addr, "GET", path).await.1
+}
+
+/// Envoie une requête à l'interface d'administration et retourne la ligne de statut et le corps de la réponse.
+pub async fn admin_request(addr: &str, method: &str, path: &str) -> (String, String) {
+    let mut socket = tokio::net::TcpStream::connect(addr).await.unwrap();
+    socket.write_all(format!("{} {} HTTP/1.1\r\nHost: {}\r\n\r\n", method, path, addr).as_bytes()).await.unwrap();
+    let mut response = String::new();
+    socket.read_to_string(&mut response).await.unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap_or_default();
+    (head.lines().next().unwrap_or_default().to_string(), body.to_string())
+}
+
+/// Ouvre une connexion depuis l'adresse locale donnée, envoie `ping` et indique si la réponse
+/// `pong` du serveur cible est relayée. La connexion est retournée pour pouvoir la garder ouverte.
+pub async fn served_from(local: &str, addr: SocketAddr) -> (bool, tokio::net::TcpStream) {
+    let socket = tokio::net::TcpSocket::new_v4().unwrap();
+    socket.bind(format!("{}:0", local).parse().unwrap()).unwrap();
+    let mut client = socket.connect(addr).await.unwrap();
+    let _ = client.write_all(b"ping").await;
+    let mut buf = [0; 16];
+    let served = matches!(
+        tokio::time::timeout(Duration::from_secs(5), client.read(&mut buf)).await.expect("connection was neither served nor closed"),
+        Ok(n) if &buf[..n] == b"pong"
+    );
+    (served, client)
+}
+
+/// Démarre un serveur cible qui répond `reply` au premier message reçu puis ferme la connexion.
+pub async fn spawn_closing_backend(reply: &'static [u8]) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            tokio::spawn(async move {
+                let mut buf = [0; 1024];
+                if matches!(socket.read(&mut buf).await, Ok(n) if n > 0) {
+                    let _ = socket.write_all(reply).await;
+                }
+            });
+        }
+    });
+    addr
+}